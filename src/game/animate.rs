@@ -1,7 +1,14 @@
 use bevy::prelude::*;
 
+use super::game;
+use crate::theme::Animation;
+use crate::theme::SpriteAnimation;
+
 pub fn plugin(app: &mut bevy::prelude::App) {
     app.add_systems(PreUpdate, Lerp::update);
+    app.add_observer(walk_on_move);
+    app.add_observer(idle_on_arrive);
+    app.add_observer(animate_attack);
     app.register_type::<Lerp>();
 }
 
@@ -53,3 +60,27 @@ impl Lerp {
         }
     }
 }
+
+fn walk_on_move(trigger: On<game::Move>, mut query: Query<&mut SpriteAnimation>) {
+    if let Ok(mut animation) = query.get_mut(trigger.event_target()) {
+        animation.play(Animation::Walk);
+    }
+}
+
+// Units stop walking once they reach the end of their path.
+fn idle_on_arrive(trigger: On<Remove, Lerp>, mut query: Query<&mut SpriteAnimation>) {
+    if let Ok(mut animation) = query.get_mut(trigger.event_target())
+        && animation.current() == Animation::Walk
+    {
+        animation.play(Animation::Idle);
+    }
+}
+
+fn animate_attack(trigger: On<game::Attack>, mut query: Query<&mut SpriteAnimation>) {
+    if let Ok(mut animation) = query.get_mut(trigger.event_target()) {
+        animation.play(Animation::Attack);
+    }
+    if let Ok(mut animation) = query.get_mut(trigger.event().target()) {
+        animation.play(Animation::Hurt);
+    }
+}
@@ -7,6 +7,7 @@ use bevy::sprite_render::Material2d;
 use bevy::sprite_render::Material2dPlugin;
 
 use crate::random::RandomSource;
+use crate::random::RandomStream;
 
 pub fn plugin(app: &mut App) {
     app.add_systems(PostUpdate, scale_background.after(camera_system));
@@ -28,7 +29,7 @@ fn spawn_background(
     commands.spawn((
         Mesh2d(meshes.add(Rectangle::default())),
         MeshMaterial2d(materials.add(BackgroundMaterial::new(
-            rand.as_mut(),
+            rand.cosmetic(),
             Color::srgb_u8(6, 30, 41),
             Color::srgb_u8(29, 84, 109),
             Color::srgb_u8(95, 149, 152),
@@ -73,7 +74,7 @@ impl Material2d for BackgroundMaterial {
 }
 
 impl BackgroundMaterial {
-    fn new(rand: &mut RandomSource, highlight: Color, base: Color, accent: Color) -> Self {
+    fn new(rand: &mut RandomStream, highlight: Color, base: Color, accent: Color) -> Self {
         BackgroundMaterial {
             seed: rand.random(),
             highlight: highlight.to_linear(),
@@ -1,6 +1,7 @@
 use bevy::input::mouse::MouseScrollUnit;
 use bevy::input::mouse::MouseWheel;
 use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
 
 pub fn plugin(app: &mut App) {
     app.insert_resource(CameraControls::default());
@@ -97,7 +98,13 @@ fn control_camera(
 fn camera_zoom(
     mut input: MessageReader<MouseWheel>,
     mut query: Query<&mut Projection, With<ControlledCamera>>,
+    panel_query: Query<&RelativeCursorPosition>,
 ) {
+    // The mouse wheel scrolls panels instead while the cursor is over them.
+    if panel_query.iter().any(|cursor| cursor.cursor_over()) {
+        input.clear();
+        return;
+    }
     for mut camera in query.iter_mut() {
         match &mut *camera {
             Projection::Orthographic(ortho) => {
@@ -135,3 +142,14 @@ fn move_camera_event(
         controller.reset();
     }
 }
+
+// Returns the world position under the cursor, if the cursor is over the window.
+pub fn cursor_position(
+    window: &Window,
+    camera: &Camera,
+    transform: &GlobalTransform,
+) -> Option<Vec2> {
+    window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(transform, cursor).ok())
+}
@@ -0,0 +1,519 @@
+use bevy::ecs::relationship::Relationship;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::camera;
+use super::grid;
+use super::grid::selection;
+use super::unit;
+use crate::game::animate::Lerp;
+use crate::random::RandomSource;
+
+pub fn plugin(app: &mut App) {
+    app.add_observer(apply_formation);
+    app.add_observer(deploy_at);
+    app.add_observer(confirm_deployment);
+
+    app.add_systems(
+        Update,
+        (
+            deploy_click.run_if(input_just_pressed(MouseButton::Left)),
+            deploy_keys,
+        ),
+    );
+
+    app.register_type::<Deployment>();
+    app.register_type::<DeployZone>();
+}
+
+#[derive(Clone, Debug, Reflect)]
+// The spaces a team may place its units in before the battle begins.
+pub struct DeployZone {
+    pub team: u32,
+    pub shape: selection::Shape,
+    // The direction formations placed in the zone face.
+    pub facing: IVec2,
+    pub confirmed: bool,
+}
+
+impl DeployZone {
+    pub fn new(team: u32, shape: selection::Shape, facing: IVec2) -> Self {
+        DeployZone {
+            team,
+            shape,
+            facing,
+            confirmed: false,
+        }
+    }
+}
+
+#[derive(Component, Clone, Debug, Reflect)]
+// Added to a grid while teams place their units. Turns do not begin until every team confirms and it is removed.
+pub struct Deployment {
+    pub zones: Vec<DeployZone>,
+    // Index of the zone being deployed.
+    active: usize,
+    // Unit chosen to be moved or swapped by the next click.
+    selected: Option<Entity>,
+}
+
+impl Deployment {
+    // Starts with the first team that has not yet confirmed.
+    pub fn new(zones: Vec<DeployZone>) -> Self {
+        Deployment {
+            active: zones.iter().position(|zone| !zone.confirmed).unwrap_or(0),
+            zones,
+            selected: None,
+        }
+    }
+
+    pub fn active(&self) -> Option<&DeployZone> {
+        self.zones.get(self.active)
+    }
+
+    pub fn selected(&self) -> Option<Entity> {
+        self.selected
+    }
+
+    // Returns true once every team has confirmed its deployment.
+    pub fn is_ready(&self) -> bool {
+        self.zones.iter().all(|zone| zone.confirmed)
+    }
+
+    // Moves on to the next team that has not yet confirmed, clearing the selection.
+    pub fn next_team(&mut self) {
+        self.selected = None;
+        let count = self.zones.len();
+        if let Some(next) = (1..=count)
+            .map(|step| (self.active + step) % count)
+            .find(|index| !self.zones[*index].confirmed)
+        {
+            self.active = next;
+        }
+    }
+
+    // Marks the team as confirmed and moves on to the next team. Returns true if the team was waiting to confirm.
+    pub fn confirm(&mut self, team: u32) -> bool {
+        let Some(zone) = self
+            .zones
+            .iter_mut()
+            .find(|zone| zone.team == team && !zone.confirmed)
+        else {
+            return false;
+        };
+        zone.confirmed = true;
+        self.next_team();
+        true
+    }
+}
+
+// Arranges the units of a team into a formation within its deploy zone.
+#[derive(EntityEvent, Clone, Debug, Reflect)]
+pub struct ApplyFormation {
+    entity: Entity,
+    team: u32,
+    formation: selection::Formation,
+}
+
+impl ApplyFormation {
+    pub fn new(entity: Entity, team: u32, formation: selection::Formation) -> Self {
+        ApplyFormation {
+            entity,
+            team,
+            formation,
+        }
+    }
+}
+
+// Confirms the deployment of a team, starting the battle once every team has confirmed.
+#[derive(EntityEvent, Clone, Debug, Reflect)]
+pub struct ConfirmDeployment {
+    entity: Entity,
+    team: u32,
+}
+
+impl ConfirmDeployment {
+    pub fn new(entity: Entity, team: u32) -> Self {
+        ConfirmDeployment { entity, team }
+    }
+}
+
+// Clicks a space of a grid being deployed, placing or swapping the units of the deploying team.
+#[derive(EntityEvent, Clone, Debug, Reflect)]
+pub struct DeployAt {
+    entity: Entity,
+    location: IVec2,
+}
+
+impl DeployAt {
+    pub fn new(entity: Entity, location: IVec2) -> Self {
+        DeployAt { entity, location }
+    }
+}
+
+fn deploy_keys(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut commands: Commands,
+    mut deploy_query: Query<(Entity, &mut Deployment)>,
+) {
+    for (entity, mut deployment) in deploy_query.iter_mut() {
+        let Some(team) = deployment.active().map(|zone| zone.team) else {
+            continue;
+        };
+        for (key, formation) in [
+            (KeyCode::Digit1, selection::Formation::Line),
+            (KeyCode::Digit2, selection::Formation::Wedge),
+            (KeyCode::Digit3, selection::Formation::Block),
+        ] {
+            if keyboard_input.just_pressed(key) {
+                commands.trigger(ApplyFormation::new(entity, team, formation));
+            }
+        }
+        if keyboard_input.just_pressed(KeyCode::Tab) {
+            deployment.next_team();
+        }
+        if keyboard_input.just_pressed(KeyCode::Enter) {
+            commands.trigger(ConfirmDeployment::new(entity, team));
+        }
+    }
+}
+
+// Deploys at the space under the cursor on every grid being deployed.
+fn deploy_click(
+    mut commands: Commands,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<camera::ControlledCamera>>,
+    deploy_query: Query<(Entity, &grid::Grid, &grid::GridScale), With<Deployment>>,
+) {
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (window_query.single(), camera_query.single())
+    else {
+        return;
+    };
+    let Some(cursor) = camera::cursor_position(window, camera, camera_transform) else {
+        return;
+    };
+    for (entity, grid, scale) in deploy_query.iter() {
+        if let Some(location) = scale.location(grid, &cursor) {
+            commands.trigger(DeployAt::new(entity, location));
+        }
+    }
+}
+
+// Selects a unit of the deploying team, then moves it to an empty space or swaps it with another unit in the zone.
+fn deploy_at(
+    trigger: On<DeployAt>,
+    mut commands: Commands,
+    mut deploy_query: Query<(&mut Deployment, &mut grid::Grid, &grid::GridScale)>,
+    mut unit_query: Query<(&mut grid::GridLocation, &Transform, &unit::Unit)>,
+) {
+    let location = trigger.event().location;
+    let Ok((mut deployment, mut grid, scale)) = deploy_query.get_mut(trigger.event_target()) else {
+        return;
+    };
+    let Some(zone) = deployment.active().cloned() else {
+        return;
+    };
+    if !zone.shape.contains(&location) {
+        deployment.selected = None;
+        return;
+    }
+    let occupant = grid
+        .get_entity(&grid::EntityKind::Unit, &location)
+        .filter(|entity| {
+            unit_query
+                .get(*entity)
+                .is_ok_and(|(_, _, u)| u.team == zone.team)
+        });
+    let moved = match (deployment.selected, occupant) {
+        (Some(selected), Some(other)) if selected != other => {
+            let Ok([(mut a, a_transform, _), (mut b, b_transform, _)]) =
+                unit_query.get_many_mut([selected, other])
+            else {
+                return;
+            };
+            if grid.swap(&mut a, &mut b) {
+                vec![
+                    (selected, a_transform.translation, *a.location()),
+                    (other, b_transform.translation, *b.location()),
+                ]
+            } else {
+                Vec::new()
+            }
+        }
+        (Some(selected), None) => {
+            let Ok((mut from, transform, _)) = unit_query.get_mut(selected) else {
+                return;
+            };
+            if grid.move_to(&mut from, &location).is_some() {
+                vec![(selected, transform.translation, location)]
+            } else {
+                Vec::new()
+            }
+        }
+        (_, Some(other)) => {
+            deployment.selected = (deployment.selected != Some(other)).then_some(other);
+            return;
+        }
+        (None, None) => return,
+    };
+    for (entity, from, to) in moved {
+        commands.entity(entity).insert(Lerp::new(
+            vec![from, scale.translation(&grid, &to, from.z as i32)],
+            0.1,
+        ));
+    }
+    deployment.selected = None;
+}
+
+fn apply_formation(
+    trigger: On<ApplyFormation>,
+    mut commands: Commands,
+    mut deploy_query: Query<(&mut Deployment, &mut grid::Grid, &grid::GridScale)>,
+    mut unit_query: Query<(
+        Entity,
+        &mut grid::GridLocation,
+        &Transform,
+        &unit::Unit,
+        &grid::GridOwner,
+    )>,
+    mut rand: ResMut<RandomSource>,
+) {
+    let event = trigger.event();
+    let grid_entity = trigger.event_target();
+    let Ok((mut deployment, mut grid, scale)) = deploy_query.get_mut(grid_entity) else {
+        return;
+    };
+    let Some(zone) = deployment
+        .zones
+        .iter()
+        .find(|zone| zone.team == event.team && !zone.confirmed)
+        .cloned()
+    else {
+        return;
+    };
+    let mut members: Vec<Entity> = unit_query
+        .iter()
+        .filter(|(_, _, _, unit, owner)| unit.team == zone.team && owner.get() == grid_entity)
+        .map(|(entity, ..)| entity)
+        .collect();
+    members.sort();
+    // Spaces held by the team's own units are free, they will be moved or swapped out of the way.
+    let targets = zone.shape.place(
+        rand.map(),
+        grid.size(),
+        members.len(),
+        &selection::Placement::Formation(event.formation, zone.facing),
+        |location| {
+            grid.can_enter(&grid::EntityKind::Unit, location)
+                || grid
+                    .get_entity(&grid::EntityKind::Unit, location)
+                    .is_some_and(|entity| members.contains(&entity))
+        },
+    );
+    let starts: Vec<Vec3> = members
+        .iter()
+        .filter_map(|entity| unit_query.get(*entity).ok())
+        .map(|(_, _, transform, ..)| transform.translation)
+        .collect();
+    // Each unit in turn moves to its target, swapping with any later unit already standing there.
+    for (index, target) in targets.iter().enumerate() {
+        let entity = members[index];
+        match grid.get_entity(&grid::EntityKind::Unit, target) {
+            Some(other) if other == entity => {}
+            Some(other) => {
+                if let Ok([mut a, mut b]) = unit_query.get_many_mut([entity, other]) {
+                    grid.swap(&mut a.1, &mut b.1);
+                }
+            }
+            None => {
+                if let Ok((_, mut location, ..)) = unit_query.get_mut(entity) {
+                    grid.move_to(&mut location, target);
+                }
+            }
+        }
+    }
+    for (entity, start) in members.iter().zip(starts) {
+        if let Ok((_, location, ..)) = unit_query.get(*entity) {
+            commands.entity(*entity).insert((
+                Lerp::new(
+                    vec![
+                        start,
+                        scale.translation(&grid, location.location(), start.z as i32),
+                    ],
+                    0.1,
+                ),
+                unit::Facing::new(zone.facing),
+            ));
+        }
+    }
+    deployment.selected = None;
+}
+
+fn confirm_deployment(
+    trigger: On<ConfirmDeployment>,
+    mut commands: Commands,
+    mut deploy_query: Query<&mut Deployment>,
+) {
+    let Ok(mut deployment) = deploy_query.get_mut(trigger.event_target()) else {
+        return;
+    };
+    if deployment.confirm(trigger.event().team) && deployment.is_ready() {
+        info!("All teams deployed, the battle begins");
+        commands
+            .entity(trigger.event_target())
+            .remove::<Deployment>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deployment() -> Deployment {
+        Deployment::new(
+            (1..=3)
+                .map(|team| DeployZone::new(team, selection::Shape::All, IVec2::Y))
+                .collect(),
+        )
+    }
+
+    // Spawns a grid deploying a single team into the bottom rows, with the team's units in the first columns.
+    fn deploy_app(units: i32) -> (App, Entity, Vec<Entity>) {
+        let mut app = App::new();
+        app.add_plugins(grid::plugin);
+        app.init_resource::<RandomSource>();
+        app.add_observer(apply_formation);
+        app.add_observer(deploy_at);
+
+        let world = app.world_mut();
+        let grid_entity = world.spawn_empty().id();
+        let mut grid = grid::Grid::new(IVec2::new(5, 5));
+        let mut commands = world.commands();
+        let members = (0..units)
+            .map(|x| {
+                grid.spawn(
+                    &mut commands,
+                    &grid::EntityKind::Unit,
+                    &IVec2::new(x, 0),
+                    grid_entity,
+                    unit::Unit { team: 1 },
+                )
+                .unwrap()
+            })
+            .collect();
+        world.flush();
+        world.entity_mut(grid_entity).insert((
+            grid,
+            grid::GridScale::new(IVec2::new(16, 16)),
+            Deployment::new(vec![DeployZone::new(
+                1,
+                selection::Shape::Square(IVec2::ZERO, IVec2::new(5, 2)),
+                IVec2::Y,
+            )]),
+        ));
+        (app, grid_entity, members)
+    }
+
+    // Returns the unit the grid holds at the location.
+    fn unit_at(world: &World, grid_entity: Entity, location: IVec2) -> Option<Entity> {
+        world
+            .get::<grid::Grid>(grid_entity)
+            .and_then(|grid| grid.get_entity(&grid::EntityKind::Unit, &location))
+    }
+
+    #[test]
+    fn test_deploy_at_swaps_and_moves() {
+        let (mut app, grid_entity, members) = deploy_app(2);
+        let world = app.world_mut();
+
+        // Selecting one unit and then another swaps them.
+        world.trigger(DeployAt::new(grid_entity, IVec2::new(0, 0)));
+        world.trigger(DeployAt::new(grid_entity, IVec2::new(1, 0)));
+        world.flush();
+        assert_eq!(
+            unit_at(world, grid_entity, IVec2::new(0, 0)),
+            Some(members[1])
+        );
+        assert_eq!(
+            unit_at(world, grid_entity, IVec2::new(1, 0)),
+            Some(members[0])
+        );
+
+        // Selecting a unit and then an empty space moves it there.
+        world.trigger(DeployAt::new(grid_entity, IVec2::new(1, 0)));
+        world.trigger(DeployAt::new(grid_entity, IVec2::new(3, 1)));
+        world.flush();
+        assert_eq!(unit_at(world, grid_entity, IVec2::new(1, 0)), None);
+        assert_eq!(
+            unit_at(world, grid_entity, IVec2::new(3, 1)),
+            Some(members[0])
+        );
+        let location = world.get::<grid::GridLocation>(members[0]).unwrap();
+        assert_eq!(location.location(), &IVec2::new(3, 1));
+
+        // Spaces outside the zone clear the selection instead.
+        world.trigger(DeployAt::new(grid_entity, IVec2::new(0, 0)));
+        world.trigger(DeployAt::new(grid_entity, IVec2::new(2, 4)));
+        world.flush();
+        assert_eq!(unit_at(world, grid_entity, IVec2::new(2, 4)), None);
+        let deployment = world.get::<Deployment>(grid_entity).unwrap();
+        assert_eq!(deployment.selected(), None);
+    }
+
+    #[test]
+    fn test_apply_formation_places_team_in_zone() {
+        let (mut app, grid_entity, members) = deploy_app(3);
+        let world = app.world_mut();
+
+        world.trigger(ApplyFormation::new(
+            grid_entity,
+            1,
+            selection::Formation::Line,
+        ));
+        world.flush();
+
+        let zone = selection::Shape::Square(IVec2::ZERO, IVec2::new(5, 2));
+        for member in members {
+            let location = *world.get::<grid::GridLocation>(member).unwrap().location();
+            assert!(zone.contains(&location));
+            assert_eq!(unit_at(world, grid_entity, location), Some(member));
+            assert_eq!(
+                world.get::<unit::Facing>(member).map(|f| *f.direction()),
+                Some(IVec2::Y)
+            );
+        }
+    }
+
+    #[test]
+    fn test_next_team_skips_confirmed() {
+        let mut deployment = deployment();
+        deployment.zones[1].confirmed = true;
+        deployment.next_team();
+        assert_eq!(deployment.active().map(|z| z.team), Some(3));
+        deployment.next_team();
+        assert_eq!(deployment.active().map(|z| z.team), Some(1));
+    }
+
+    #[test]
+    fn test_new_skips_confirmed() {
+        let mut zones = deployment().zones;
+        zones[0].confirmed = true;
+        assert_eq!(Deployment::new(zones).active().map(|z| z.team), Some(2));
+    }
+
+    #[test]
+    fn test_confirm() {
+        let mut deployment = deployment();
+        assert!(deployment.confirm(1));
+        assert!(!deployment.confirm(1));
+        assert_eq!(deployment.active().map(|z| z.team), Some(2));
+        assert!(deployment.confirm(3));
+        assert!(!deployment.is_ready());
+        assert_eq!(deployment.active().map(|z| z.team), Some(2));
+        assert!(deployment.confirm(2));
+        assert!(deployment.is_ready());
+        assert!(!deployment.confirm(4));
+    }
+}
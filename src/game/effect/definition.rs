@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::particle::EmitterDefinition;
+use super::projectile::ProjectileDefinition;
+use crate::theme::data::DataFile;
+
+// Copy of the effects built into the game, used when the file can not be loaded.
+const BUILT_IN_EFFECTS: &str = include_str!("../../../assets/effects.ron");
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+// The easing functions effects can use, named as in the effects file.
+pub enum Ease {
+    #[default]
+    Linear,
+    QuadraticIn,
+    QuadraticOut,
+    CubicIn,
+    CubicOut,
+    SineInOut,
+    BackOut,
+}
+
+impl Ease {
+    fn function(&self) -> EaseFunction {
+        match self {
+            Ease::Linear => EaseFunction::Linear,
+            Ease::QuadraticIn => EaseFunction::QuadraticIn,
+            Ease::QuadraticOut => EaseFunction::QuadraticOut,
+            Ease::CubicIn => EaseFunction::CubicIn,
+            Ease::CubicOut => EaseFunction::CubicOut,
+            Ease::SineInOut => EaseFunction::SineInOut,
+            Ease::BackOut => EaseFunction::BackOut,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+// Eases a value from one number to another.
+pub struct Step {
+    pub from: f32,
+    pub to: f32,
+    #[serde(default)]
+    pub ease: Ease,
+}
+
+impl Step {
+    pub fn new(from: f32, to: f32, ease: Ease) -> Self {
+        Step { from, to, ease }
+    }
+}
+
+// Samples a sequence of steps, each taking an equal share of the effect's duration.
+// Returns nothing for an empty sequence, leaving the value it would animate alone.
+pub fn sample(steps: &[Step], fraction: f32) -> Option<f32> {
+    let last = steps.last()?;
+    let position = fraction.clamp(0.0, 1.0) * steps.len() as f32;
+    let index = position.floor() as usize;
+    let Some(step) = steps.get(index) else {
+        return Some(last.to);
+    };
+    EasingCurve::new(step.from, step.to, step.ease.function()).sample(position - index as f32)
+}
+
+#[derive(Clone, Debug, Default)]
+// The values an effect animates over its life.
+pub struct Tracks {
+    // Distance along the effect's path, from its start to its end.
+    pub translation: Vec<Step>,
+    // Degrees turned from the direction of the path.
+    pub rotation: Vec<Step>,
+    pub scale: Vec<Step>,
+    pub alpha: Vec<Step>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+// How an effect looks and moves between a source and a target.
+pub struct EffectDefinition {
+    // Name of the theme texture to draw.
+    pub sprite: String,
+    // Color multiplied with the texture, as red, green, blue and alpha.
+    #[serde(default = "default_tint")]
+    pub tint: (f32, f32, f32, f32),
+    // Seconds the effect lasts.
+    pub duration: f32,
+    // Where the path starts and ends, as fractions of the way from the source to the target.
+    #[serde(default = "default_offsets")]
+    pub offsets: (f32, f32),
+    #[serde(default)]
+    pub translation: Vec<Step>,
+    #[serde(default)]
+    pub rotation: Vec<Step>,
+    #[serde(default)]
+    pub scale: Vec<Step>,
+    #[serde(default)]
+    pub alpha: Vec<Step>,
+}
+
+fn default_tint() -> (f32, f32, f32, f32) {
+    (1.0, 1.0, 1.0, 1.0)
+}
+
+fn default_offsets() -> (f32, f32) {
+    (0.0, 1.0)
+}
+
+impl EffectDefinition {
+    pub fn tint(&self) -> Color {
+        let (red, green, blue, alpha) = self.tint;
+        Color::srgba(red, green, blue, alpha)
+    }
+
+    pub fn tracks(&self) -> Tracks {
+        Tracks {
+            translation: self.translation.clone(),
+            rotation: self.rotation.clone(),
+            scale: self.scale.clone(),
+            alpha: self.alpha.clone(),
+        }
+    }
+}
+
+#[derive(Asset, Resource, Clone, Debug, Default, Deserialize, TypePath)]
+// Every effect, particle emitter and projectile the game can show, by name.
+pub struct EffectDefinitions {
+    effects: HashMap<String, EffectDefinition>,
+    #[serde(default)]
+    emitters: HashMap<String, EmitterDefinition>,
+    #[serde(default)]
+    projectiles: HashMap<String, ProjectileDefinition>,
+}
+
+impl DataFile for EffectDefinitions {
+    const PATH: &'static str = "effects.ron";
+    const BUILT_IN: &'static str = BUILT_IN_EFFECTS;
+}
+
+impl EffectDefinitions {
+    pub fn get(&self, name: &str) -> Option<&EffectDefinition> {
+        self.effects.get(name)
+    }
+
+    pub fn emitter(&self, name: &str) -> Option<&EmitterDefinition> {
+        self.emitters.get(name)
+    }
+
+    pub fn projectile(&self, name: &str) -> Option<&ProjectileDefinition> {
+        self.projectiles.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_effects() {
+        let definitions: EffectDefinitions = ron::from_str(BUILT_IN_EFFECTS).unwrap();
+        for name in ["swing", "blast"] {
+            assert!(definitions.get(name).is_some(), "Missing {}", name);
+        }
+        for name in ["shoot", "bomb"] {
+            assert!(definitions.projectile(name).is_some(), "Missing {}", name);
+        }
+        assert_eq!(definitions.projectile("shoot").unwrap().arc, 0.0);
+        assert_eq!(definitions.get("swing").unwrap().translation.len(), 2);
+        for name in ["blood", "sparks", "dust", "explosion"] {
+            assert!(definitions.emitter(name).is_some(), "Missing {}", name);
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let definition: EffectDefinition =
+            ron::from_str("(sprite: \"swing\", duration: 0.5, scale: [(from: 1.0, to: 2.0)])")
+                .unwrap();
+        assert_eq!(definition.offsets, (0.0, 1.0));
+        assert_eq!(definition.tint(), Color::srgb(1.0, 1.0, 1.0));
+        assert_eq!(definition.scale[0].ease, Ease::Linear);
+        assert!(definition.translation.is_empty());
+    }
+
+    #[test]
+    fn test_sample() {
+        let steps = [
+            Step::new(0.0, 1.0, Ease::Linear),
+            Step::new(1.0, 0.0, Ease::Linear),
+        ];
+        assert_eq!(sample(&steps, 0.0), Some(0.0));
+        assert_eq!(sample(&steps, 0.25), Some(0.5));
+        assert_eq!(sample(&steps, 0.5), Some(1.0));
+        assert_eq!(sample(&steps, 0.75), Some(0.5));
+        assert_eq!(sample(&steps, 1.0), Some(0.0));
+        assert_eq!(sample(&[], 0.5), None);
+    }
+}
@@ -0,0 +1,391 @@
+use bevy::prelude::*;
+
+use super::unit;
+use crate::random::RandomSource;
+use crate::theme;
+use crate::util::cords;
+
+mod definition;
+mod particle;
+mod projectile;
+
+pub use definition::EffectDefinitions;
+pub use particle::Emitter;
+pub use projectile::Impact;
+
+const EFFECT_Z_LAYER: f32 = 900.0;
+// How far combat text rises while it fades, in pixels.
+const TEXT_RISE: f32 = 24.0;
+const TEXT_DURATION: f32 = 0.8;
+
+pub fn plugin(app: &mut App) {
+    app.add_plugins((
+        particle::plugin,
+        projectile::plugin,
+        theme::data::plugin::<EffectDefinitions>,
+    ));
+    app.add_observer(spawn_effect);
+    app.add_systems(Update, (process_effects, update_motions).chain());
+}
+
+#[derive(Event, Clone, Debug, Reflect)]
+pub enum Effect {
+    // Shows the named effect from the effects file between a source and a target.
+    Play(String, Vec2, Vec2),
+    // Sends out the named particles from a point, from the effects file.
+    Emit(String, Vec2),
+    // Fires the named projectile from the effects file at a target, triggering an impact where it lands.
+    Launch { name: String, impact: Impact },
+    // Floats text above an entity, rising and fading away.
+    Text(Entity, CombatText),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+// What happened to an entity's health, shown as floating text.
+pub enum CombatText {
+    Damage(u32),
+    // Damage increased by attacking into the side or back.
+    Flanked(u32),
+    Heal(u32),
+    Miss,
+}
+
+impl CombatText {
+    // Describes a change in health. Health that did not change is shown as a miss.
+    pub fn change(before: u32, after: u32) -> Self {
+        if after < before {
+            CombatText::Damage(before - after)
+        } else if after > before {
+            CombatText::Heal(after - before)
+        } else {
+            CombatText::Miss
+        }
+    }
+
+    // Shows damage as flanking damage when the attack struck the side or back.
+    pub fn flanked(self, flanked: bool) -> Self {
+        match self {
+            CombatText::Damage(amount) if flanked => CombatText::Flanked(amount),
+            text => text,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            CombatText::Damage(amount) => format!("{}", amount),
+            CombatText::Flanked(amount) => format!("{}!", amount),
+            CombatText::Heal(amount) => format!("+{}", amount),
+            CombatText::Miss => "Miss".to_string(),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            CombatText::Damage(_) => Color::srgb(1.0, 1.0, 1.0),
+            CombatText::Flanked(_) => Color::srgb(1.0, 0.6, 0.0),
+            CombatText::Heal(_) => Color::srgb(0.3, 1.0, 0.3),
+            CombatText::Miss => Color::srgb(0.6, 0.6, 0.6),
+        }
+    }
+
+    // Particles sent out by the hit, if any.
+    fn particles(&self) -> Option<&'static str> {
+        match self {
+            CombatText::Damage(_) => Some("blood"),
+            CombatText::Flanked(_) => Some("sparks"),
+            CombatText::Heal(_) | CombatText::Miss => None,
+        }
+    }
+
+    fn font_size(&self) -> f32 {
+        match self {
+            CombatText::Flanked(_) => 20.0,
+            _ => 14.0,
+        }
+    }
+}
+
+#[derive(Component)]
+struct EffectTimer {
+    pub timer: Timer,
+}
+
+impl EffectTimer {
+    fn new(duration: f32) -> Self {
+        EffectTimer {
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+        }
+    }
+}
+
+// Damages an entity and floats the damage taken above it, marked when it struck the side or back.
+pub fn damage(
+    commands: &mut Commands,
+    entity: Entity,
+    health: &mut unit::Health,
+    amount: u32,
+    flanked: bool,
+) {
+    let before = health.total();
+    health.damage(amount);
+    commands.trigger(Effect::Text(
+        entity,
+        CombatText::change(before, health.total()).flanked(flanked),
+    ));
+}
+
+// Heals an entity and floats the health restored above it.
+pub fn heal(commands: &mut Commands, entity: Entity, health: &mut unit::Health, amount: u32) {
+    let before = health.current;
+    health.heal(amount);
+    commands.trigger(Effect::Text(
+        entity,
+        CombatText::change(before, health.current),
+    ));
+}
+
+// Places an emitter on its own, which despawns once it has finished emitting.
+fn spawn_emitter(
+    commands: &mut Commands,
+    definitions: &EffectDefinitions,
+    name: &str,
+    position: Vec3,
+) {
+    let Some(definition) = definitions.emitter(name) else {
+        warn!("No particle emitter named {}", name);
+        return;
+    };
+    commands.spawn((
+        Emitter::once(definition.clone()),
+        Transform::from_translation(position),
+        Name::new(format!("Emitter {}", name)),
+    ));
+}
+
+fn spawn_effect(
+    trigger: On<Effect>,
+    mut commands: Commands,
+    sprites: Res<theme::Textures>,
+    definitions: Res<EffectDefinitions>,
+    mut random: ResMut<RandomSource>,
+    transform_query: Query<&Transform>,
+) {
+    match trigger.event() {
+        Effect::Text(entity, text) => {
+            let Ok(transform) = transform_query.get(*entity) else {
+                return;
+            };
+            // Text starts just above the top of a unit standing in the space.
+            let from = (transform.translation.xy() + Vec2::new(0.0, sprites.unit.scale().y))
+                .extend(EFFECT_Z_LAYER);
+            commands.spawn((
+                EffectTimer::new(TEXT_DURATION),
+                Transform::from_translation(from),
+                EffectMotion {
+                    from,
+                    to: from + Vec3::new(0.0, TEXT_RISE, 0.0),
+                    facing: Quat::IDENTITY,
+                    alpha: 1.0,
+                    tracks: definition::Tracks {
+                        translation: vec![definition::Step::new(
+                            0.0,
+                            1.0,
+                            definition::Ease::CubicOut,
+                        )],
+                        alpha: vec![definition::Step::new(
+                            1.0,
+                            0.0,
+                            definition::Ease::QuadraticIn,
+                        )],
+                        ..default()
+                    },
+                },
+                Text2d::new(text.label()),
+                TextFont::from_font_size(text.font_size()),
+                TextColor(text.color()),
+                Name::new("CombatText"),
+            ));
+            if let Some(name) = text.particles() {
+                // Hits burst from the middle of the unit.
+                let middle =
+                    transform.translation.xy() + Vec2::new(0.0, sprites.unit.scale().y * 0.5);
+                spawn_emitter(
+                    &mut commands,
+                    &definitions,
+                    name,
+                    middle.extend(EFFECT_Z_LAYER),
+                );
+            }
+        }
+        Effect::Launch { name, impact } => {
+            let impact = impact.clone();
+            let (Some(definition), Ok(from), Ok(to)) = (
+                definitions.projectile(name),
+                transform_query.get(impact.source()),
+                transform_query.get(impact.event_target()),
+            ) else {
+                // Attacks with nothing to show land at once.
+                commands.trigger(impact);
+                return;
+            };
+            let Some(texture) = sprites.get(&definition.sprite) else {
+                warn!(
+                    "Projectile {} draws a missing texture {}",
+                    name, definition.sprite
+                );
+                commands.trigger(impact);
+                return;
+            };
+            // Projectiles fly between the middles of units.
+            let middle = Vec2::new(0.0, sprites.unit.scale().y * 0.5);
+            let from = from.translation.xy() + middle;
+            let landing = definition.landing(
+                from,
+                to.translation.xy() + middle,
+                impact.hit(),
+                random.cosmetic().ratio(1, 2),
+            );
+            let projectile = projectile::Projectile::new(
+                definition,
+                from.extend(EFFECT_Z_LAYER),
+                landing.extend(EFFECT_Z_LAYER),
+                impact,
+            );
+            commands.spawn((
+                Transform::from_translation(from.extend(EFFECT_Z_LAYER))
+                    .with_rotation(projectile.rotation()),
+                Sprite {
+                    color: definition.tint(),
+                    ..texture.sprite()
+                },
+                projectile,
+                Name::new(format!("Projectile {}", name)),
+            ));
+        }
+        Effect::Emit(name, position) => {
+            spawn_emitter(
+                &mut commands,
+                &definitions,
+                name,
+                position.extend(EFFECT_Z_LAYER),
+            );
+        }
+        Effect::Play(name, source, target) => {
+            let Some(definition) = definitions.get(name) else {
+                warn!("No effect named {}", name);
+                return;
+            };
+            let Some(texture) = sprites.get(&definition.sprite) else {
+                warn!(
+                    "Effect {} draws a missing texture {}",
+                    name, definition.sprite
+                );
+                return;
+            };
+            let (start, end) = definition.offsets;
+            let from = cords::percent_between(*source, *target, start).extend(EFFECT_Z_LAYER);
+            let to = cords::percent_between(*source, *target, end).extend(EFFECT_Z_LAYER);
+            let facing = cords::quad_to(from, to);
+            let tint = definition.tint();
+            commands.spawn((
+                EffectTimer::new(definition.duration),
+                Transform::from_translation(from).with_rotation(facing),
+                EffectMotion {
+                    from,
+                    to,
+                    facing,
+                    alpha: tint.alpha(),
+                    tracks: definition.tracks(),
+                },
+                Sprite {
+                    color: tint,
+                    ..texture.sprite()
+                },
+                Name::new(format!("Effect {}", name)),
+            ));
+        }
+    }
+}
+
+fn process_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut EffectTimer)>,
+) {
+    for (entity, mut effect_instance) in query.iter_mut() {
+        effect_instance.timer.tick(time.delta());
+        if effect_instance.timer.is_finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[derive(Component)]
+// Moves, turns, scales and fades an effect along its path as its timer runs.
+struct EffectMotion {
+    from: Vec3,
+    to: Vec3,
+    facing: Quat,
+    // Opacity the alpha track is multiplied with.
+    alpha: f32,
+    tracks: definition::Tracks,
+}
+
+fn update_motions(
+    mut query: Query<(
+        &mut Transform,
+        &EffectTimer,
+        &EffectMotion,
+        Option<&mut Sprite>,
+        Option<&mut TextColor>,
+    )>,
+) {
+    for (mut transform, timer, motion, sprite, text_color) in query.iter_mut() {
+        let fraction = timer.timer.fraction();
+        let tracks = &motion.tracks;
+        if let Some(distance) = definition::sample(&tracks.translation, fraction) {
+            transform.translation = motion.from.lerp(motion.to, distance);
+        }
+        if let Some(degrees) = definition::sample(&tracks.rotation, fraction) {
+            transform.rotation = motion.facing * Quat::from_rotation_z(degrees.to_radians());
+        }
+        if let Some(scale) = definition::sample(&tracks.scale, fraction) {
+            transform.scale = Vec3::splat(scale);
+        }
+        if let Some(alpha) = definition::sample(&tracks.alpha, fraction) {
+            let alpha = alpha * motion.alpha;
+            if let Some(mut sprite) = sprite {
+                sprite.color.set_alpha(alpha);
+            }
+            if let Some(mut color) = text_color {
+                color.0.set_alpha(alpha);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change() {
+        assert_eq!(CombatText::change(10, 7), CombatText::Damage(3));
+        assert_eq!(CombatText::change(7, 10), CombatText::Heal(3));
+        assert_eq!(CombatText::change(7, 7), CombatText::Miss);
+    }
+
+    #[test]
+    fn test_flanked() {
+        assert_eq!(
+            CombatText::change(10, 4).flanked(true),
+            CombatText::Flanked(6)
+        );
+        assert_eq!(
+            CombatText::change(10, 4).flanked(false),
+            CombatText::Damage(6)
+        );
+        assert_eq!(CombatText::Miss.flanked(true), CombatText::Miss);
+        assert_eq!(CombatText::Flanked(6).label(), "6!");
+    }
+}
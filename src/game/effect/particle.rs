@@ -0,0 +1,306 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystems;
+use serde::Deserialize;
+
+use super::EffectDefinitions;
+use crate::game::animate::Lerp;
+use crate::game::unit;
+use crate::random::RandomSource;
+use crate::random::RandomStream;
+
+// Emitted by units while they walk.
+const DUST: &str = "dust";
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(ParticleLimit::default());
+    app.add_observer(raise_dust);
+    app.add_observer(settle_dust);
+
+    app.add_systems(Update, update_particles);
+    // Emitters read where they are after transforms have been propagated.
+    app.add_systems(
+        PostUpdate,
+        update_emitters.after(TransformSystems::Propagate),
+    );
+
+    app.register_type::<ParticleLimit>();
+}
+
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource)]
+// Most particles alive at once. Emitters skip particles while the limit is reached.
+pub struct ParticleLimit {
+    pub max: usize,
+}
+
+impl Default for ParticleLimit {
+    fn default() -> Self {
+        ParticleLimit { max: 400 }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+// How an emitter sends out particles, read from the effects file.
+pub struct EmitterDefinition {
+    // Particles emitted at once when the emitter starts.
+    #[serde(default)]
+    pub burst: u32,
+    // Particles emitted each second after the burst.
+    #[serde(default)]
+    pub rate: f32,
+    // Seconds an emitter placed on its own keeps emitting. Emitters on an entity emit until removed.
+    #[serde(default)]
+    pub duration: f32,
+    // Pixels each second, between a minimum and a maximum.
+    pub speed: (f32, f32),
+    // Degrees counterclockwise from the right, and the spread of directions around it.
+    #[serde(default)]
+    pub direction: f32,
+    #[serde(default = "default_spread")]
+    pub spread: f32,
+    // Pixels each second added to the velocity every second. Negative y falls.
+    #[serde(default)]
+    pub gravity: (f32, f32),
+    // Seconds each particle lives, between a minimum and a maximum.
+    pub lifetime: (f32, f32),
+    // Width of each square particle in pixels.
+    pub size: f32,
+    // Colors a particle fades from and to over its life, as red, green, blue and alpha.
+    pub start_color: (f32, f32, f32, f32),
+    pub end_color: (f32, f32, f32, f32),
+    // Drawn this far in front of the emitter, or behind when negative.
+    #[serde(default)]
+    pub layer: f32,
+}
+
+fn default_spread() -> f32 {
+    360.0
+}
+
+fn color((red, green, blue, alpha): (f32, f32, f32, f32)) -> Color {
+    Color::srgba(red, green, blue, alpha)
+}
+
+fn between(random: &mut RandomStream, (min, max): (f32, f32)) -> f32 {
+    if min < max {
+        random.range(min..=max)
+    } else {
+        min
+    }
+}
+
+impl EmitterDefinition {
+    // Creates a particle leaving the origin in a random direction within the spread.
+    fn particle(&self, random: &mut RandomStream) -> Particle {
+        let half = self.spread * 0.5;
+        let angle = (self.direction + between(random, (-half, half))).to_radians();
+        Particle {
+            velocity: Vec2::from_angle(angle) * between(random, self.speed),
+            gravity: Vec2::new(self.gravity.0, self.gravity.1),
+            age: 0.0,
+            lifetime: between(random, self.lifetime),
+            start: color(self.start_color),
+            end: color(self.end_color),
+        }
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+// Sends out particles from wherever its entity is.
+pub struct Emitter {
+    definition: EmitterDefinition,
+    // Seconds the emitter lasts before despawning its entity, or forever when none.
+    lasts: Option<f32>,
+    elapsed: f32,
+    burst: bool,
+    // Part of a particle owed by the rate, emitted once it adds up to a whole one.
+    owed: f32,
+}
+
+impl Emitter {
+    // Emits until removed from its entity.
+    pub fn new(definition: EmitterDefinition) -> Self {
+        Emitter {
+            definition,
+            lasts: None,
+            elapsed: 0.0,
+            burst: false,
+            owed: 0.0,
+        }
+    }
+
+    // Emits for the definition's duration, then despawns its entity.
+    pub fn once(definition: EmitterDefinition) -> Self {
+        let duration = definition.duration;
+        Emitter {
+            lasts: Some(duration),
+            ..Emitter::new(definition)
+        }
+    }
+
+    // Advances the emitter, returning how many particles it emits.
+    fn due(&mut self, delta: f32) -> u32 {
+        let mut count = 0;
+        if !self.burst {
+            self.burst = true;
+            count += self.definition.burst;
+        }
+        self.owed += self.definition.rate * delta;
+        count += self.owed.floor() as u32;
+        self.owed = self.owed.fract();
+        self.elapsed += delta;
+        count
+    }
+
+    fn finished(&self) -> bool {
+        self.lasts
+            .is_some_and(|lasts| self.burst && self.elapsed >= lasts)
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+struct Particle {
+    velocity: Vec2,
+    gravity: Vec2,
+    age: f32,
+    lifetime: f32,
+    start: Color,
+    end: Color,
+}
+
+impl Particle {
+    // Moves the particle, returning whether it is still alive.
+    fn update(&mut self, transform: &mut Transform, delta: f32) -> bool {
+        self.age += delta;
+        self.velocity += self.gravity * delta;
+        transform.translation += (self.velocity * delta).extend(0.0);
+        self.age < self.lifetime
+    }
+
+    fn color(&self) -> Color {
+        self.start
+            .mix(&self.end, (self.age / self.lifetime).clamp(0.0, 1.0))
+    }
+}
+
+fn update_emitters(
+    mut commands: Commands,
+    time: Res<Time>,
+    limit: Res<ParticleLimit>,
+    mut random: ResMut<RandomSource>,
+    mut emitter_query: Query<(Entity, &mut Emitter, &GlobalTransform)>,
+    particle_query: Query<(), With<Particle>>,
+) {
+    let mut alive = particle_query.iter().count();
+    let random = random.cosmetic();
+    for (entity, mut emitter, transform) in emitter_query.iter_mut() {
+        let due = emitter.due(time.delta_secs()) as usize;
+        let count = due.min(limit.max.saturating_sub(alive));
+        alive += count;
+        let definition = &emitter.definition;
+        let origin = transform.translation() + Vec3::Z * definition.layer;
+        for _ in 0..count {
+            let particle = definition.particle(random);
+            let transform = Transform::from_translation(origin);
+            commands.spawn((
+                Sprite::from_color(particle.color(), Vec2::splat(definition.size)),
+                transform,
+                // Drawn where it starts on the first frame, before transforms are propagated again.
+                GlobalTransform::from(transform),
+                particle,
+                Name::new("Particle"),
+            ));
+        }
+        if emitter.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in query.iter_mut() {
+        if particle.update(&mut transform, time.delta_secs()) {
+            sprite.color = particle.color();
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn raise_dust(
+    trigger: On<Add, Lerp>,
+    mut commands: Commands,
+    definitions: Res<EffectDefinitions>,
+    unit_query: Query<(), With<unit::Unit>>,
+) {
+    if !unit_query.contains(trigger.event_target()) {
+        return;
+    }
+    if let Some(definition) = definitions.emitter(DUST) {
+        commands
+            .entity(trigger.event_target())
+            .insert(Emitter::new(definition.clone()));
+    }
+}
+
+fn settle_dust(trigger: On<Remove, Lerp>, mut commands: Commands) {
+    if let Ok(mut entity) = commands.get_entity(trigger.event_target()) {
+        entity.try_remove::<Emitter>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition() -> EmitterDefinition {
+        ron::from_str(
+            "(
+                burst: 5,
+                rate: 10.0,
+                speed: (10.0, 20.0),
+                gravity: (0.0, -100.0),
+                lifetime: (1.0, 1.0),
+                size: 2.0,
+                start_color: (1.0, 0.0, 0.0, 1.0),
+                end_color: (1.0, 0.0, 0.0, 0.0),
+            )",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_due() {
+        let mut emitter = Emitter::new(definition());
+        // The burst comes first, then the rate adds up over time.
+        assert_eq!(emitter.due(0.05), 5);
+        assert_eq!(emitter.due(0.05), 1);
+        assert_eq!(emitter.due(0.25), 2);
+        assert!(!emitter.finished());
+
+        let mut emitter = Emitter::once(definition());
+        assert!(!emitter.finished());
+        emitter.due(0.0);
+        assert!(emitter.finished());
+    }
+
+    #[test]
+    fn test_particle() {
+        let definition = definition();
+        let mut random = RandomStream::new(0);
+        let mut particle = definition.particle(&mut random);
+        let speed = particle.velocity.length();
+        assert!((10.0..=20.0).contains(&speed), "Speed {}", speed);
+
+        let mut transform = Transform::default();
+        assert!(particle.update(&mut transform, 0.5));
+        assert_eq!(particle.color().alpha(), 0.5);
+        // Gravity pulls the particle down over its life.
+        assert!(particle.velocity.y < 0.0);
+        assert!(!particle.update(&mut transform, 0.5));
+    }
+}
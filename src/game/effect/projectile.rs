@@ -0,0 +1,256 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::game::forced::Forced;
+use crate::util::cords;
+
+// Projectiles take at least this long, so shots at close range can still be seen.
+const MIN_FLIGHT: f32 = 0.1;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(Update, update_projectiles);
+}
+
+#[derive(Clone, Debug, Deserialize)]
+// How a projectile looks and flies, read from the effects file.
+pub struct ProjectileDefinition {
+    // Name of the theme texture to draw, facing the way it flies.
+    pub sprite: String,
+    // Color multiplied with the texture, as red, green, blue and alpha.
+    #[serde(default = "default_tint")]
+    pub tint: (f32, f32, f32, f32),
+    // Pixels each second along the ground.
+    pub speed: f32,
+    // Height of the arc at its peak as a fraction of the distance flown. Thrown weapons arc, arrows fly
+    // straight when zero.
+    #[serde(default)]
+    pub arc: f32,
+    // Pixels a miss lands past the target, off to one side.
+    #[serde(default = "default_miss")]
+    pub miss: f32,
+    // Name of the particle emitter started where the projectile lands.
+    #[serde(default)]
+    pub impact: Option<String>,
+}
+
+fn default_tint() -> (f32, f32, f32, f32) {
+    (1.0, 1.0, 1.0, 1.0)
+}
+
+fn default_miss() -> f32 {
+    16.0
+}
+
+impl ProjectileDefinition {
+    pub fn tint(&self) -> Color {
+        let (red, green, blue, alpha) = self.tint;
+        Color::srgba(red, green, blue, alpha)
+    }
+
+    // Where a projectile lands, past the target and to one side when it misses.
+    pub fn landing(&self, from: Vec2, target: Vec2, hit: bool, left: bool) -> Vec2 {
+        if hit {
+            return target;
+        }
+        let direction = (target - from).normalize_or(Vec2::X);
+        let side = if left {
+            direction.perp()
+        } else {
+            -direction.perp()
+        };
+        target + (direction + side) * self.miss
+    }
+}
+
+#[derive(EntityEvent, Clone, Debug, Reflect)]
+// Triggered on the target of an attack when the attack lands, or would have landed if it missed.
+// Damage is applied on impact, so it shows at the moment the projectile arrives. What the attack
+// does is decided when it is made, so it still lands if the source is gone by then.
+pub struct Impact {
+    entity: Entity,
+    source: Entity,
+    // Attacks that do no damage miss.
+    damage: u32,
+    // Struck the side or back of the target.
+    flanked: bool,
+    forced: Option<Forced>,
+}
+
+impl Impact {
+    pub fn new(target: Entity, source: Entity, damage: u32) -> Self {
+        Impact {
+            entity: target,
+            source,
+            damage,
+            flanked: false,
+            forced: None,
+        }
+    }
+
+    pub fn flanked(mut self, flanked: bool) -> Self {
+        self.flanked = flanked;
+        self
+    }
+
+    pub fn forced(mut self, forced: Option<Forced>) -> Self {
+        self.forced = forced;
+        self
+    }
+
+    pub fn source(&self) -> Entity {
+        self.source
+    }
+
+    pub fn damage(&self) -> u32 {
+        self.damage
+    }
+
+    pub fn is_flanked(&self) -> bool {
+        self.flanked
+    }
+
+    pub fn forced_move(&self) -> Option<&Forced> {
+        self.forced.as_ref()
+    }
+
+    pub fn hit(&self) -> bool {
+        self.damage > 0
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+// Flies from a source to where it lands, rising and falling along its arc.
+pub struct Projectile {
+    from: Vec3,
+    to: Vec3,
+    // Height of the arc at its peak in pixels.
+    height: f32,
+    duration: f32,
+    elapsed: f32,
+    impact: Impact,
+    // Emitter started where it lands.
+    emitter: Option<String>,
+}
+
+impl Projectile {
+    pub fn new(definition: &ProjectileDefinition, from: Vec3, to: Vec3, impact: Impact) -> Self {
+        let distance = from.xy().distance(to.xy());
+        Projectile {
+            from,
+            to,
+            height: distance * definition.arc,
+            duration: (distance / definition.speed.max(f32::EPSILON)).max(MIN_FLIGHT),
+            elapsed: 0.0,
+            impact,
+            emitter: definition.impact.clone(),
+        }
+    }
+
+    fn fraction(&self) -> f32 {
+        (self.elapsed / self.duration).clamp(0.0, 1.0)
+    }
+
+    // Position along the path, raised by a parabola that peaks halfway.
+    fn position(&self) -> Vec3 {
+        let fraction = self.fraction();
+        self.from.lerp(self.to, fraction)
+            + Vec3::Y * self.height * 4.0 * fraction * (1.0 - fraction)
+    }
+
+    // Direction of travel, which turns downward over the peak of an arc.
+    fn velocity(&self) -> Vec3 {
+        let fraction = self.fraction();
+        (self.to - self.from) + Vec3::Y * self.height * 4.0 * (1.0 - 2.0 * fraction)
+    }
+
+    // The rotation facing the direction of travel.
+    pub fn rotation(&self) -> Quat {
+        cords::quad_to(Vec3::ZERO, self.velocity())
+    }
+}
+
+fn update_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Projectile, &mut Transform)>,
+) {
+    for (entity, mut projectile, mut transform) in query.iter_mut() {
+        projectile.elapsed += time.delta_secs();
+        transform.translation = projectile.position();
+        transform.rotation = projectile.rotation();
+        if projectile.elapsed >= projectile.duration {
+            commands.trigger(projectile.impact.clone());
+            if let Some(name) = &projectile.emitter {
+                commands.trigger(super::Effect::Emit(name.clone(), projectile.to.truncate()));
+            }
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(arc: f32) -> ProjectileDefinition {
+        ProjectileDefinition {
+            sprite: "attack".to_string(),
+            tint: default_tint(),
+            speed: 100.0,
+            arc,
+            miss: 10.0,
+            impact: None,
+        }
+    }
+
+    #[test]
+    fn test_arc() {
+        let impact = Impact::new(Entity::PLACEHOLDER, Entity::PLACEHOLDER, 1);
+        let mut projectile = Projectile::new(
+            &definition(0.5),
+            Vec3::ZERO,
+            Vec3::new(100.0, 0.0, 0.0),
+            impact,
+        );
+        assert_eq!(projectile.duration, 1.0);
+        // Rising on the way up, at its peak halfway and falling on the way down.
+        assert!(projectile.velocity().y > 0.0);
+        projectile.elapsed = 0.5;
+        assert_eq!(projectile.position(), Vec3::new(50.0, 50.0, 0.0));
+        assert_eq!(projectile.velocity().y, 0.0);
+        projectile.elapsed = 0.75;
+        assert!(projectile.velocity().y < 0.0);
+        projectile.elapsed = 1.0;
+        assert_eq!(projectile.position(), Vec3::new(100.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_straight() {
+        let impact = Impact::new(Entity::PLACEHOLDER, Entity::PLACEHOLDER, 1);
+        let mut projectile = Projectile::new(
+            &definition(0.0),
+            Vec3::ZERO,
+            Vec3::new(0.0, 5.0, 0.0),
+            impact,
+        );
+        assert_eq!(projectile.duration, MIN_FLIGHT);
+        projectile.elapsed = MIN_FLIGHT / 2.0;
+        assert_eq!(projectile.position(), Vec3::new(0.0, 2.5, 0.0));
+    }
+
+    #[test]
+    fn test_landing() {
+        let definition = definition(0.0);
+        let from = Vec2::ZERO;
+        let target = Vec2::new(100.0, 0.0);
+        assert_eq!(definition.landing(from, target, true, true), target);
+        assert_eq!(
+            definition.landing(from, target, false, true),
+            Vec2::new(110.0, 10.0)
+        );
+        assert_eq!(
+            definition.landing(from, target, false, false),
+            Vec2::new(110.0, -10.0)
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use bevy::ecs::relationship::Relationship;
+use bevy::prelude::*;
+
+use super::effect;
+use super::game;
+use super::grid;
+use super::unit;
+use crate::game::animate::Lerp;
+
+// Damage dealt for each space of movement lost to a collision.
+const COLLISION_DAMAGE: u32 = 2;
+
+pub fn plugin(app: &mut App) {
+    app.add_observer(do_forced_move);
+
+    app.register_type::<Forced>();
+}
+
+#[derive(Clone, Debug, Reflect)]
+// Movement applied to an entity by another, rather than chosen by it.
+pub enum Forced {
+    // Pushes the target away from the source by a number of spaces.
+    Push(u32),
+    // Pulls the target towards the source by a number of spaces.
+    Pull(u32),
+    // Swaps the locations of the source and target.
+    Swap,
+}
+
+#[derive(EntityEvent, Clone, Debug, Reflect)]
+// Forces an entity to move, whether from an attack that carries forced movement or anything else
+// that shoves entities around, such as a barrel's blast.
+pub struct ForcedMove {
+    entity: Entity,
+    source: Entity,
+    forced: Forced,
+}
+
+impl ForcedMove {
+    pub fn new(entity: Entity, source: Entity, forced: Forced) -> Self {
+        ForcedMove {
+            entity,
+            source,
+            forced,
+        }
+    }
+}
+
+fn do_forced_move(
+    trigger: On<ForcedMove>,
+    mut commands: Commands,
+    mut unit_query: Query<(&mut grid::GridLocation, &Transform, &grid::GridOwner)>,
+    mut grid_query: Query<(&mut grid::Grid, &grid::GridScale)>,
+    mut health_query: Query<&mut unit::Health>,
+) {
+    let event = trigger.event();
+    let target = trigger.event_target();
+    let Ok(
+        [
+            (mut target_location, target_transform, owner),
+            (mut source_location, source_transform, _),
+        ],
+    ) = unit_query.get_many_mut([target, event.source])
+    else {
+        return;
+    };
+    // Objects keep their blocking and cost in the grid, so only units can be moved.
+    if *target_location.kind() != grid::EntityKind::Unit {
+        return;
+    }
+    let Ok((mut grid, scale)) = grid_query.get_mut(owner.get()) else {
+        return;
+    };
+    let from = *target_location.location();
+    let direction = (from - source_location.location()).signum();
+    let (path, collision) = match event.forced {
+        Forced::Push(distance) => grid.slide(target_location.kind(), &from, &direction, distance),
+        Forced::Pull(distance) => {
+            // Pulled entities stop next to the source rather than colliding with it.
+            let between = (from - source_location.location()).abs().max_element() as u32;
+            let (path, _) = grid.slide(
+                target_location.kind(),
+                &from,
+                &-direction,
+                distance.min(between.saturating_sub(1)),
+            );
+            (path, None)
+        }
+        Forced::Swap => {
+            if grid.swap(&mut target_location, &mut source_location) {
+                let source_to = *source_location.location();
+                commands.entity(event.source).insert(Lerp::new(
+                    vec![
+                        source_transform.translation,
+                        scale.translation(&grid, &source_to, source_transform.translation.z as i32),
+                    ],
+                    0.1,
+                ));
+                (vec![from, *target_location.location()], None)
+            } else {
+                (vec![from], None)
+            }
+        }
+    };
+
+    if let Forced::Push(distance) | Forced::Pull(distance) = event.forced {
+        if let Some(to) = path.last() {
+            grid.move_to(&mut target_location, to);
+        }
+        if let Some(collided) = collision {
+            // Both the target and whatever it hit take damage for the movement lost.
+            let lost = distance + 1 - path.len() as u32;
+            let damage = COLLISION_DAMAGE * lost;
+            if let Ok(mut health) = health_query.get_mut(target) {
+                effect::damage(&mut commands, target, &mut health, damage, false);
+            }
+            for kind in [grid::EntityKind::Unit, grid::EntityKind::Object] {
+                if let Some(entity) = grid.get_entity(&kind, &collided)
+                    && let Ok(mut health) = health_query.get_mut(entity)
+                {
+                    effect::damage(&mut commands, entity, &mut health, damage, false);
+                }
+            }
+        }
+    }
+
+    if path.len() > 1 {
+        for step in path.iter().skip(1) {
+            commands.trigger(game::EnterSpace::new(target, *step));
+        }
+        commands.entity(target).insert(Lerp::new(
+            path.iter()
+                .map(|location| {
+                    scale.translation(&grid, location, target_transform.translation.z as i32)
+                })
+                .collect(),
+            0.1,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Spawns units with plenty of health at each location, returning them in order.
+    fn spawn_units<const N: usize>(
+        world: &mut World,
+        size: IVec2,
+        locations: [IVec2; N],
+    ) -> (Entity, [Entity; N]) {
+        let grid_entity = world.spawn_empty().id();
+        let mut grid = grid::Grid::new(size);
+        let mut commands = world.commands();
+        let units = locations.map(|location| {
+            grid.spawn(
+                &mut commands,
+                &grid::EntityKind::Unit,
+                &location,
+                grid_entity,
+                unit::Health::new(10),
+            )
+            .unwrap()
+        });
+        world.flush();
+        world
+            .entity_mut(grid_entity)
+            .insert((grid, grid::GridScale::new(IVec2::new(16, 16))));
+        (grid_entity, units)
+    }
+
+    fn forced_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(grid::plugin);
+        app.add_observer(do_forced_move);
+        app
+    }
+
+    #[test]
+    fn test_push_collides_with_unit() {
+        let mut app = forced_app();
+        let world = app.world_mut();
+        let (grid_entity, [source, target, blocker]) = spawn_units(
+            world,
+            IVec2::new(5, 1),
+            [IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(3, 0)],
+        );
+
+        world.trigger(ForcedMove::new(target, source, Forced::Push(3)));
+        world.flush();
+
+        // Stopped one space short by the blocker, so both take damage for the two spaces lost.
+        let grid = world.get::<grid::Grid>(grid_entity).unwrap();
+        assert_eq!(
+            grid.get_entity(&grid::EntityKind::Unit, &IVec2::new(2, 0)),
+            Some(target)
+        );
+        assert_eq!(world.get::<unit::Health>(target).unwrap().current, 6);
+        assert_eq!(world.get::<unit::Health>(blocker).unwrap().current, 6);
+        assert_eq!(world.get::<unit::Health>(source).unwrap().current, 10);
+    }
+
+    #[test]
+    fn test_push_stops_at_grid_edge() {
+        let mut app = forced_app();
+        let world = app.world_mut();
+        let (grid_entity, [source, target]) = spawn_units(
+            world,
+            IVec2::new(4, 1),
+            [IVec2::new(1, 0), IVec2::new(2, 0)],
+        );
+
+        world.trigger(ForcedMove::new(target, source, Forced::Push(3)));
+        world.flush();
+
+        // The edge of the grid stops the push like a wall would.
+        let location = world.get::<grid::GridLocation>(target).unwrap();
+        assert_eq!(location.location(), &IVec2::new(3, 0));
+        let grid = world.get::<grid::Grid>(grid_entity).unwrap();
+        assert_eq!(
+            grid.get_entity(&grid::EntityKind::Unit, &IVec2::new(3, 0)),
+            Some(target)
+        );
+        assert_eq!(world.get::<unit::Health>(target).unwrap().current, 6);
+    }
+}
@@ -4,8 +4,12 @@ use bevy::prelude::*;
 use super::grid;
 use super::unit;
 use crate::game::animate::Lerp;
+use crate::game::deploy;
 use crate::game::effect;
-use crate::util::cords;
+use crate::game::forced;
+use crate::game::object;
+use crate::game::team;
+use crate::game::zone;
 
 pub fn plugin(app: &mut bevy::prelude::App) {
     app.add_systems(PreUpdate, TurnOrder::next_turn);
@@ -13,6 +17,7 @@ pub fn plugin(app: &mut bevy::prelude::App) {
     app.add_observer(do_turn);
     app.add_observer(do_move);
     app.add_observer(do_attack);
+    app.add_observer(do_impact);
     app.register_type::<TurnOrder>();
 }
 
@@ -21,10 +26,18 @@ pub struct Turn {
     entity: Entity,
 }
 
+// Triggered on the grid entity before the entities of a turn act.
+#[derive(EntityEvent, Clone, Debug, Reflect)]
+pub struct TurnStart {
+    entity: Entity,
+}
+
 #[derive(Component, Clone, Debug, Reflect)]
 pub struct TurnOrder {
     pub order: Vec<Vec<Entity>>,
     pub index: usize,
+    // Number of times the turn order has started from the top. Zero until the first turn.
+    pub round: u32,
 }
 
 impl Default for TurnOrder {
@@ -32,6 +45,7 @@ impl Default for TurnOrder {
         TurnOrder {
             order: Vec::new(),
             index: 0,
+            round: 0,
         }
     }
 }
@@ -53,10 +67,12 @@ impl TurnOrder {
     pub fn next_turn(
         keyboard_input: Res<ButtonInput<KeyCode>>,
         mut commands: Commands,
-        mut turn_order: Query<&mut TurnOrder>,
+        // Battles do not begin until every team has deployed.
+        mut turn_order: Query<(Entity, &mut TurnOrder), Without<deploy::Deployment>>,
     ) {
         if keyboard_input.just_pressed(KeyCode::Space) {
-            for mut turn in turn_order.iter_mut() {
+            for (entity, mut turn) in turn_order.iter_mut() {
+                commands.trigger(TurnStart { entity });
                 if let Some(next) = turn.get_next_entity() {
                     for entity in next {
                         commands.trigger(Turn {
@@ -73,6 +89,9 @@ impl TurnOrder {
             return None;
         }
         let current = self.index;
+        if current == 0 {
+            self.round += 1;
+        }
         self.index = (self.index + 1) % self.order.len();
         Some(&self.order[current])
     }
@@ -81,89 +100,267 @@ impl TurnOrder {
 fn do_turn(
     trigger: On<Turn>,
     mut commands: Commands,
-    unit_query: Query<(&grid::GridLocation, &unit::Unit, &unit::Attacks)>,
+    unit_query: Query<(
+        &grid::GridLocation,
+        &unit::Unit,
+        &unit::Attacks,
+        Option<&unit::Facing>,
+    )>,
     target_query: Query<&unit::Unit>,
+    object_query: Query<(Entity, &object::MapObject, &grid::GridLocation)>,
     grid_query: Query<&mut grid::Grid>,
+    teams: Res<team::Teams>,
 ) {
-    if let Ok((location, unit, attacks)) = unit_query.get(trigger.event_target()) {
+    let entity = trigger.event_target();
+    if let Ok((location, unit, attacks, facing)) = unit_query.get(entity) {
         if let Ok(grid) = grid_query.single() {
+            let in_range =
+                |target: &IVec2| grid.in_range(location.location(), target, attacks.range);
+            let hostile = |entity: &Entity| {
+                target_query
+                    .get(entity.clone())
+                    .map_or(false, |u| teams.is_hostile(unit.team, u.team))
+            };
+            let allied = |entity: &Entity| {
+                target_query
+                    .get(entity.clone())
+                    .map_or(false, |u| teams.is_allied(unit.team, u.team))
+            };
+            if let Some(barrel) = barrel_target(grid, &object_query, in_range, hostile, allied) {
+                commands.trigger(Attack {
+                    entity,
+                    target: barrel,
+                });
+                return;
+            }
+            // Units look for targets in the direction they are facing first.
+            let direction = facing.map_or(IVec2::new(1, 0), |f| *f.direction());
             if let Some(target_location) = grid.nearest_entity(
                 &super::grid::EntityKind::Unit,
                 location.location(),
-                &IVec2::new(1, 0),
+                &direction,
                 grid::selection::Shape::All,
-                |entity| {
-                    target_query
-                        .get(entity.clone())
-                        .map_or(false, |u| u.team != unit.team)
-                },
+                hostile,
             ) {
-                if target_location
-                    .as_vec2()
-                    .distance_squared(location.location().as_vec2())
-                    <= attacks.range * attacks.range
-                {
+                if in_range(&target_location) {
                     commands.trigger(Attack {
-                        entity: trigger.event_target(),
+                        entity,
                         target: grid
                             .get_entity(&super::grid::EntityKind::Unit, &target_location)
                             .unwrap(),
                     });
-                } else {
-                    commands.trigger(Move {
-                        entity: trigger.event_target(),
-                        towards: target_location,
-                    });
+                    return;
+                }
+                // Objects walling the target off are broken through on the way.
+                let blocking = grid
+                    .a_star_to(
+                        &super::grid::EntityKind::Unit,
+                        location.location(),
+                        &target_location,
+                        0,
+                    )
+                    .is_empty()
+                    .then(|| {
+                        grid.first_blocking(
+                            &super::grid::EntityKind::Unit,
+                            location.location(),
+                            &target_location,
+                        )
+                    })
+                    .flatten();
+                match blocking.and_then(|blocking| {
+                    grid.get_entity(&super::grid::EntityKind::Object, &blocking)
+                        .map(|object| (blocking, object))
+                }) {
+                    Some((blocking, object)) if in_range(&blocking) => {
+                        commands.trigger(Attack {
+                            entity,
+                            target: object,
+                        });
+                    }
+                    Some((blocking, _)) => {
+                        commands.trigger(Move {
+                            entity,
+                            towards: blocking,
+                        });
+                    }
+                    None => {
+                        commands.trigger(Move {
+                            entity,
+                            towards: target_location,
+                        });
+                    }
                 }
             }
         }
     }
 }
 
+// Finds a barrel within range whose blast would catch enemies without catching any allies.
+fn barrel_target(
+    grid: &grid::Grid,
+    object_query: &Query<(Entity, &object::MapObject, &grid::GridLocation)>,
+    in_range: impl Fn(&IVec2) -> bool,
+    hostile: impl Fn(&Entity) -> bool,
+    allied: impl Fn(&Entity) -> bool,
+) -> Option<Entity> {
+    object_query.iter().find_map(|(entity, object, location)| {
+        let object::MapObject::Barrel { radius, .. } = object else {
+            return None;
+        };
+        if !in_range(location.location()) {
+            return None;
+        }
+        let blast = grid::selection::Shape::Circle(location.location().as_vec2(), *radius);
+        let caught: Vec<Entity> = grid
+            .entities_within(&grid::EntityKind::Unit, blast)
+            .map(|(_, unit)| unit)
+            .collect();
+        (caught.iter().any(&hostile) && !caught.iter().any(&allied)).then_some(entity)
+    })
+}
+
 #[derive(EntityEvent, Clone, Debug, Reflect)]
 pub struct Move {
     entity: Entity,
     towards: IVec2,
 }
 
+// Triggered once a move has found its path, before the entity sets off along it.
+#[derive(EntityEvent, Clone, Debug, Reflect)]
+pub struct Moved {
+    entity: Entity,
+    path: Vec<IVec2>,
+}
+
+impl Moved {
+    // Every space along the way, from where the entity started to where it stops.
+    pub fn path(&self) -> &[IVec2] {
+        &self.path
+    }
+}
+
+// Triggered for every space an entity passes through while moving.
+#[derive(EntityEvent, Clone, Debug, Reflect)]
+pub struct EnterSpace {
+    entity: Entity,
+    location: IVec2,
+}
+
+impl EnterSpace {
+    pub fn new(entity: Entity, location: IVec2) -> Self {
+        EnterSpace { entity, location }
+    }
+
+    pub fn location(&self) -> &IVec2 {
+        &self.location
+    }
+}
+
 fn do_move(
     trigger: On<Move>,
     mut commands: Commands,
-    mut unit_query: Query<(
-        &mut grid::GridLocation,
-        &Transform,
-        &unit::Movement,
-        &grid::GridOwner,
+    mut unit_query: ParamSet<(
+        Query<(
+            &mut grid::GridLocation,
+            &Transform,
+            &unit::Movement,
+            &grid::GridOwner,
+            Option<&unit::Facing>,
+        )>,
+        Query<(Entity, &unit::Unit, &grid::GridLocation)>,
     )>,
     mut grid_query: Query<(&mut grid::Grid, &grid::GridScale)>,
+    object_query: Query<&object::MapObject>,
+    rules: Res<zone::ZoneOfControl>,
+    teams: Res<team::Teams>,
 ) {
-    if let Ok((mut location, transform, movement, grid_owner)) =
-        unit_query.get_mut(trigger.event_target())
-    {
-        if let Ok((mut grid, grid_scale)) = grid_query.get_mut(grid_owner.get()) {
-            let steps = grid.a_star_next_to(
-                &super::grid::EntityKind::Unit,
-                location.location(),
-                &trigger.event().towards,
-                movement.spaces as usize,
-            );
-            if steps.len() > 1 {
-                grid.move_to(&mut location, steps.last().unwrap());
-                commands.entity(trigger.event_target()).insert((Lerp::new(
-                    steps
-                        .iter()
-                        .map(|loc| {
-                            cords::location_to_translation(
-                                loc,
-                                grid_scale.scale(),
-                                transform.translation.z as i32,
-                            )
-                        })
-                        .collect(),
-                    0.2,
-                ),));
+    let entity = trigger.event_target();
+    let units = unit_query.p1();
+    let Ok((_, unit, _)) = units.get(entity) else {
+        return;
+    };
+    let zone = zone::Zone::hostile_to(
+        &teams,
+        unit.team,
+        units
+            .iter()
+            .map(|(e, u, location)| (e, u.team, *location.location())),
+    );
+    let mut unit_query = unit_query.p0();
+    let Ok((mut location, transform, movement, grid_owner, facing)) = unit_query.get_mut(entity)
+    else {
+        return;
+    };
+    let Ok((mut grid, grid_scale)) = grid_query.get_mut(grid_owner.get()) else {
+        return;
+    };
+    let mut steps = grid.a_star_next_to(
+        &super::grid::EntityKind::Unit,
+        location.location(),
+        &trigger.event().towards,
+        movement.spaces,
+        |location| zone.leave_cost(&rules, location),
+    );
+    // Units stop in front of closed doors and open them instead of walking through.
+    if let Some((index, door)) = steps.iter().enumerate().skip(1).find_map(|(i, loc)| {
+        grid.get_entity(&super::grid::EntityKind::Object, loc)
+            .filter(|e| object_query.get(*e).is_ok_and(|o| o.is_closed_door()))
+            .map(|e| (i, e))
+    }) {
+        commands.trigger(object::ToggleDoor::new(door));
+        steps.truncate(index);
+    }
+    if steps.len() > 1 {
+        grid.move_to(&mut location, steps.last().unwrap());
+        commands.trigger(Moved {
+            entity,
+            path: steps.clone(),
+        });
+        for step in steps.iter().skip(1) {
+            commands.trigger(EnterSpace {
+                entity,
+                location: *step,
+            });
+        }
+        // Enemies get a free attack on units leaving the spaces next to them.
+        let mut contested = Vec::new();
+        let mut attackers: Vec<Entity> = Vec::new();
+        for pair in steps.windows(2) {
+            let opportunities = zone.opportunities(&pair[0], &pair[1]);
+            if !opportunities.is_empty() {
+                contested.push(pair[0]);
+            }
+            for attacker in opportunities {
+                if !attackers.contains(&attacker) {
+                    attackers.push(attacker);
+                }
+            }
+        }
+        if rules.opportunity_attacks {
+            for attacker in attackers {
+                commands.trigger(Attack::new(attacker, entity));
             }
         }
+        // Units face the direction of their last step.
+        if let Some(facing) = facing {
+            commands
+                .entity(entity)
+                .insert(facing.towards(&steps[steps.len() - 2], &steps[steps.len() - 1]));
+        }
+        commands.entity(entity).insert((
+            Lerp::new(
+                steps
+                    .iter()
+                    .map(|loc| grid_scale.translation(&grid, loc, transform.translation.z as i32))
+                    .collect(),
+                0.2,
+            ),
+            zone::MoveReplay {
+                path: steps,
+                contested,
+            },
+        ));
     }
 }
 
@@ -173,29 +370,280 @@ pub struct Attack {
     target: Entity,
 }
 
+impl Attack {
+    pub fn new(entity: Entity, target: Entity) -> Self {
+        Attack { entity, target }
+    }
+
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+}
+
 fn do_attack(
     trigger: On<Attack>,
-    unit_query: Query<(&Transform, &unit::Attacks)>,
-    mut target_query: Query<(&Transform, &mut unit::Health)>,
+    definitions: Res<effect::EffectDefinitions>,
+    unit_query: Query<(
+        &Transform,
+        &unit::Attacks,
+        Option<&grid::GridLocation>,
+        Option<&unit::Facing>,
+    )>,
+    target_query: Query<(
+        &Transform,
+        Option<&grid::GridLocation>,
+        Option<&unit::Facing>,
+    )>,
     mut commands: Commands,
 ) {
-    if let Ok((target_transform, mut health)) = target_query.get_mut(trigger.event().target) {
-        if let Ok((source_transform, attacks)) = unit_query.get(trigger.event_target()) {
-            health.damage(attacks.damage);
-            let source = source_transform.translation.truncate();
-            let target = target_transform.translation.truncate();
-            if attacks.range <= 1.5 {
-                commands.trigger(effect::Effect::Swing(
-                    cords::percent_between(source, target, 0.25),
-                    cords::percent_between(source, target, 0.75),
-                ));
-                return;
-            } else {
-                commands.trigger(effect::Effect::Shoot(
-                    cords::percent_between(source, target, 0.25),
-                    cords::percent_between(source, target, 0.75),
-                ));
-            }
+    let source = trigger.event_target();
+    let target = trigger.event().target;
+    let (
+        Ok((target_transform, target_location, target_facing)),
+        Ok((source_transform, attacks, source_location, source_facing)),
+    ) = (target_query.get(target), unit_query.get(source))
+    else {
+        return;
+    };
+    if let (Some(facing), Some(target), Some(source)) =
+        (source_facing, target_location, source_location)
+    {
+        commands
+            .entity(trigger.event_target())
+            .insert(facing.towards(source.location(), target.location()));
+    }
+    let side = attack_side(target_facing, target_location, source_location);
+    let impact = effect::Impact::new(target, source, side.apply(attacks.damage))
+        .flanked(side != unit::Side::Front)
+        .forced(attacks.forced.clone());
+    if definitions.projectile(&attacks.effect).is_some() {
+        commands.trigger(effect::Effect::Launch {
+            name: attacks.effect.clone(),
+            impact,
+        });
+    } else {
+        commands.trigger(impact);
+        commands.trigger(effect::Effect::Play(
+            attacks.effect.clone(),
+            source_transform.translation.truncate(),
+            target_transform.translation.truncate(),
+        ));
+    }
+}
+
+// Attacks into the side or back of a unit deal bonus damage.
+fn attack_side(
+    target_facing: Option<&unit::Facing>,
+    target_location: Option<&grid::GridLocation>,
+    source_location: Option<&grid::GridLocation>,
+) -> unit::Side {
+    match (target_facing, target_location, source_location) {
+        (Some(facing), Some(target), Some(source)) => {
+            facing.side(target.location(), source.location())
         }
+        _ => unit::Side::Front,
+    }
+}
+
+// Damages the target of an attack as it lands, which for projectiles is when they arrive.
+fn do_impact(
+    trigger: On<effect::Impact>,
+    mut health_query: Query<&mut unit::Health>,
+    mut commands: Commands,
+) {
+    let impact = trigger.event();
+    let target = trigger.event_target();
+    let Ok(mut health) = health_query.get_mut(target) else {
+        return;
+    };
+    if !impact.hit() {
+        commands.trigger(effect::Effect::Text(target, effect::CombatText::Miss));
+        return;
+    }
+    effect::damage(
+        &mut commands,
+        target,
+        &mut health,
+        impact.damage(),
+        impact.is_flanked(),
+    );
+    if let Some(forced) = impact.forced_move() {
+        commands.trigger(forced::ForcedMove::new(
+            target,
+            impact.source(),
+            forced.clone(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Spawns a unit of the team that attacks up to three spaces away.
+    fn spawn_unit(
+        commands: &mut Commands,
+        grid: &mut grid::Grid,
+        grid_entity: Entity,
+        location: IVec2,
+        team: u32,
+    ) -> Entity {
+        grid.spawn(
+            commands,
+            &grid::EntityKind::Unit,
+            &location,
+            grid_entity,
+            (
+                unit::Unit { team },
+                unit::Health::new(10),
+                unit::Attacks::new(1, 3),
+            ),
+        )
+        .unwrap()
+    }
+
+    fn turn_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((grid::plugin, forced::plugin, object::plugin, team::plugin));
+        app.add_observer(do_turn);
+        app.add_observer(do_attack);
+        app.add_observer(do_impact);
+        // No projectiles are defined, so every attack lands at once.
+        app.init_resource::<effect::EffectDefinitions>();
+        app
+    }
+
+    #[test]
+    fn test_turn_sets_off_barrel() {
+        let mut app = turn_app();
+        let world = app.world_mut();
+        let grid_entity = world.spawn_empty().id();
+        let mut grid = grid::Grid::new(IVec2::new(6, 3));
+        let mut commands = world.commands();
+        let attacker = spawn_unit(&mut commands, &mut grid, grid_entity, IVec2::new(0, 1), 1);
+        let enemy = spawn_unit(&mut commands, &mut grid, grid_entity, IVec2::new(4, 1), 2);
+        let barrel = grid
+            .spawn(
+                &mut commands,
+                &grid::EntityKind::Object,
+                &IVec2::new(3, 1),
+                grid_entity,
+                object::MapObject::Barrel {
+                    damage: 4,
+                    radius: 1.5,
+                },
+            )
+            .unwrap();
+        world.flush();
+        world
+            .entity_mut(grid_entity)
+            .insert((grid, grid::GridScale::new(IVec2::new(16, 16))));
+
+        world.trigger(Turn { entity: attacker });
+        world.flush();
+
+        // The enemy is out of range, but the barrel next to it is not.
+        assert!(world.get_entity(barrel).is_err());
+        assert_eq!(world.get::<unit::Health>(enemy).unwrap().current, 6);
+        let location = world.get::<grid::GridLocation>(enemy).unwrap();
+        assert_eq!(location.location(), &IVec2::new(5, 1));
+        assert_eq!(world.get::<unit::Health>(attacker).unwrap().current, 10);
+    }
+
+    #[test]
+    fn test_turn_spares_allies_near_barrel() {
+        let mut app = turn_app();
+        let world = app.world_mut();
+        let grid_entity = world.spawn_empty().id();
+        let mut grid = grid::Grid::new(IVec2::new(6, 3));
+        let mut commands = world.commands();
+        let attacker = spawn_unit(&mut commands, &mut grid, grid_entity, IVec2::new(0, 1), 1);
+        let ally = spawn_unit(&mut commands, &mut grid, grid_entity, IVec2::new(4, 2), 1);
+        spawn_unit(&mut commands, &mut grid, grid_entity, IVec2::new(4, 1), 2);
+        let barrel = grid
+            .spawn(
+                &mut commands,
+                &grid::EntityKind::Object,
+                &IVec2::new(3, 1),
+                grid_entity,
+                object::MapObject::Barrel {
+                    damage: 4,
+                    radius: 1.5,
+                },
+            )
+            .unwrap();
+        world.flush();
+        world
+            .entity_mut(grid_entity)
+            .insert((grid, grid::GridScale::new(IVec2::new(16, 16))));
+
+        world.trigger(Turn { entity: attacker });
+        world.flush();
+
+        assert!(world.get_entity(barrel).is_ok());
+        assert_eq!(world.get::<unit::Health>(ally).unwrap().current, 10);
+    }
+
+    #[test]
+    fn test_turn_diagonal_facing_finds_neighbour() {
+        let mut app = turn_app();
+        let world = app.world_mut();
+        let grid_entity = world.spawn_empty().id();
+        let mut grid = grid::Grid::new(IVec2::new(5, 5));
+        let mut commands = world.commands();
+        let attacker = spawn_unit(&mut commands, &mut grid, grid_entity, IVec2::new(2, 2), 1);
+        let enemy = spawn_unit(&mut commands, &mut grid, grid_entity, IVec2::new(2, 3), 2);
+        world.flush();
+        world
+            .entity_mut(grid_entity)
+            .insert((grid, grid::GridScale::new(IVec2::new(16, 16))));
+        world
+            .entity_mut(attacker)
+            .insert(unit::Facing::new(IVec2::new(1, 1)));
+
+        world.trigger(Turn { entity: attacker });
+        world.flush();
+
+        // Searching along a diagonal still finds enemies in the spaces beside the unit.
+        assert_eq!(world.get::<unit::Health>(enemy).unwrap().current, 9);
+    }
+
+    #[test]
+    fn test_turn_attacks_blocking_wall() {
+        let mut app = turn_app();
+        let world = app.world_mut();
+        let grid_entity = world.spawn_empty().id();
+        let mut grid = grid::Grid::new(IVec2::new(6, 3));
+        let mut commands = world.commands();
+        let attacker = spawn_unit(&mut commands, &mut grid, grid_entity, IVec2::new(0, 1), 1);
+        spawn_unit(&mut commands, &mut grid, grid_entity, IVec2::new(5, 1), 2);
+        let walls = (0..3)
+            .map(|y| {
+                let location = IVec2::new(2, y);
+                grid.set_blocking(&location, true);
+                grid.spawn(
+                    &mut commands,
+                    &grid::EntityKind::Object,
+                    &location,
+                    grid_entity,
+                    (object::MapObject::Wall { health: 5 }, unit::Health::new(5)),
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        world.flush();
+        world
+            .entity_mut(grid_entity)
+            .insert((grid, grid::GridScale::new(IVec2::new(16, 16))));
+
+        world.trigger(Turn { entity: attacker });
+        world.flush();
+
+        // The walls leave no way around, so the unit breaks through the one in its way.
+        let health: Vec<u32> = walls
+            .iter()
+            .map(|wall| world.get::<unit::Health>(*wall).unwrap().current)
+            .collect();
+        assert_eq!(health, vec![5, 4, 5]);
     }
 }
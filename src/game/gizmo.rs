@@ -1,35 +1,88 @@
+use bevy::ecs::relationship::Relationship;
 use bevy::prelude::*;
 
-use super::unit;
+use super::animate::Lerp;
+use super::deploy;
+use super::grid;
+use super::pick;
+use super::zone;
 
-const HEALTH_COLOR: Color = Color::srgb(0.0, 1.0, 0.0);
+const PATH_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
+const CONTESTED_COLOR: Color = Color::srgb(1.0, 0.0, 0.0);
+const DEPLOY_COLOR: Color = Color::srgb(1.0, 1.0, 0.0);
+const HOVER_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
 
 pub fn plugin(app: &mut bevy::prelude::App) {
-    app.add_systems(Update, unit_health_gizmo);
+    app.add_systems(Update, move_replay_gizmo);
+    app.add_systems(Update, deploy_zone_gizmo);
+    app.add_systems(Update, hovered_space_gizmo);
 }
 
-fn unit_health_gizmo(
+// Replays the path of moving units while they walk it, marking spaces where they left an enemy's zone of control.
+fn move_replay_gizmo(
     mut gizmos: Gizmos,
-    unit_query: Query<(&Transform, &Sprite, &unit::Health), With<unit::Unit>>,
+    unit_query: Query<(&zone::MoveReplay, &grid::GridOwner), With<Lerp>>,
+    grid_query: Query<(&grid::Grid, &grid::GridScale)>,
 ) {
-    for (transform, sprite, health) in unit_query.iter() {
-        let size = sprite.custom_size.unwrap_or(Vec2::ZERO);
-        let location = transform.translation.xy()
-            + Vec2::new(size.x / -2.0, size.y / 2.0)
-            + Vec2::new(0.0, size.y / 10.0);
-        let width = size.x;
-        let percent = health.percent();
-        if percent < 1.0 {
-            gizmos.line_2d(
-                location,
-                location + Vec2::new(width * percent, 0.0),
-                HEALTH_COLOR,
-            );
-            gizmos.line_2d(
-                location + Vec2::new(width * percent, 0.0),
-                location + Vec2::new(width, 0.0),
-                HEALTH_COLOR.with_alpha(0.2),
+    for (replay, owner) in unit_query.iter() {
+        if let Ok((grid, scale)) = grid_query.get(owner.get()) {
+            let points = replay
+                .path
+                .iter()
+                .map(|location| scale.translation(grid, location, 0).xy());
+            gizmos.linestrip_2d(points, PATH_COLOR.with_alpha(0.5));
+            for location in replay.contested.iter() {
+                gizmos.circle_2d(
+                    scale.translation(grid, location, 0).xy(),
+                    scale.scale().x as f32 / 4.0,
+                    CONTESTED_COLOR,
+                );
+            }
+        }
+    }
+}
+
+// Outlines the spaces the deploying team can place units in, and marks the selected unit.
+fn deploy_zone_gizmo(
+    mut gizmos: Gizmos,
+    deploy_query: Query<(&deploy::Deployment, &grid::Grid, &grid::GridScale)>,
+    transform_query: Query<&Transform>,
+) {
+    for (deployment, grid, scale) in deploy_query.iter() {
+        let Some(zone) = deployment.active() else {
+            continue;
+        };
+        let size = scale.scale().as_vec2();
+        for location in zone.shape.cells(grid.size()) {
+            gizmos.rect_2d(
+                scale.translation(grid, &location, 0).xy(),
+                size * 0.9,
+                DEPLOY_COLOR.with_alpha(0.2),
             );
         }
+        if let Some(transform) = deployment
+            .selected()
+            .and_then(|entity| transform_query.get(entity).ok())
+        {
+            gizmos.circle_2d(transform.translation.xy(), size.x / 2.0, DEPLOY_COLOR);
+        }
+    }
+}
+
+// Outlines the space under the cursor.
+fn hovered_space_gizmo(
+    mut gizmos: Gizmos,
+    hovered: Res<pick::Hovered>,
+    grid_query: Query<(&grid::Grid, &grid::GridScale, &GlobalTransform)>,
+) {
+    let Some((grid_entity, location)) = hovered.space else {
+        return;
+    };
+    if let Ok((grid, scale, transform)) = grid_query.get(grid_entity) {
+        gizmos.rect_2d(
+            transform.translation().xy() + scale.translation(grid, &location, 0).xy(),
+            scale.scale().as_vec2(),
+            HOVER_COLOR.with_alpha(0.6),
+        );
     }
 }
@@ -83,12 +83,15 @@ impl<T: Default> Grid<T> {
         queue.push_back(start.clone());
         let mut visited = vec![false; self.data.len()];
         let size = self.size;
+        // Only neighbouring spaces are explored, so diagonal directions keep just their larger axis.
         let dir = if direction == IVec2::ZERO {
             IVec2::new(1, 0)
+        } else if direction.x.abs() >= direction.y.abs() {
+            IVec2::new(direction.x.signum(), 0)
         } else {
-            direction
+            IVec2::new(0, direction.y.signum())
         };
-        let rotated = IVec2::new(-direction.y, direction.x); // Rotate direction 90 degrees clockwise
+        let rotated = IVec2::new(-dir.y, dir.x); // Rotate direction 90 degrees clockwise
         std::iter::from_fn(move || {
             while let Some(location) = queue.pop_front() {
                 if location.x < 0 || location.y < 0 {
@@ -118,9 +121,21 @@ impl<T: Default> Grid<T> {
 
     // A* pathfinding algorithm to find a path from start to end.
     pub fn a_star(&self, start: &IVec2, end: &IVec2, valid: impl Fn(&T) -> bool) -> Vec<IVec2> {
+        self.a_star_weighted(start, end, |_, cell| valid(cell).then_some(1))
+    }
+
+    // A* pathfinding algorithm where each cell has a cost to enter from the previous location. Cells with no cost can not be entered.
+    pub fn a_star_weighted(
+        &self,
+        start: &IVec2,
+        end: &IVec2,
+        cost: impl Fn(&IVec2, &T) -> Option<u32>,
+    ) -> Vec<IVec2> {
+        let cost = &cost;
         if let Some((path, _)) = astar(
             start,
             |p| {
+                let from = *p;
                 vec![
                     p + IVec2::new(1, 0),
                     p + IVec2::new(-1, 0),
@@ -128,18 +143,16 @@ impl<T: Default> Grid<T> {
                     p + IVec2::new(0, -1),
                 ]
                 .into_iter()
-                .filter(|location| {
-                    if location == end {
-                        return true;
+                .filter_map(move |location| {
+                    if location == *end {
+                        return Some((location, 1));
                     }
-                    if let Some(cell) = self.get(location) {
-                        return valid(cell);
-                    }
-                    false
+                    self.get(&location)
+                        .and_then(|cell| cost(&from, cell))
+                        .map(|cost| (location, cost))
                 })
-                .map(move |location| (location, 1))
             },
-            |p| p.distance_squared(*end),
+            |p| (p - end).abs().element_sum() as u32,
             |p| p == end,
         ) {
             path
@@ -246,4 +259,23 @@ mod test_iter {
         assert_eq!(iter.next(), Some(IVec2::new(2, 2)));
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn test_iter_diagonal() {
+        let grid = Grid::<()>::new(IVec2::new(3, 3));
+        let spaces: Vec<IVec2> = grid
+            .iter_breath(IVec2::new(1, 1), IVec2::new(1, 1), selection::Shape::All)
+            .collect();
+        assert_eq!(
+            spaces[..5],
+            [
+                IVec2::new(1, 1),
+                IVec2::new(2, 1),
+                IVec2::new(1, 2),
+                IVec2::new(0, 1),
+                IVec2::new(1, 0),
+            ]
+        );
+        assert_eq!(spaces.len(), 9);
+    }
 }
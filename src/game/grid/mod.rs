@@ -1,9 +1,19 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
 use bevy::ecs::relationship::Relationship;
 use bevy::prelude::*;
 
 mod grid;
 pub mod selection;
 
+use crate::util::cords;
+
+// Highest a unit can climb in a single step.
+const MAX_CLIMB: i32 = 2;
+// Extra attack range for each level of height above the target.
+const HIGH_GROUND_RANGE: f32 = 1.0;
+
 pub fn plugin(app: &mut App) {
     app.add_observer(on_remove_grid_location);
 
@@ -18,6 +28,13 @@ pub fn plugin(app: &mut App) {
 pub struct Space {
     pub unit: Option<Entity>,
     pub tile: Option<Entity>,
+    pub object: Option<Entity>,
+    // Units can not enter a blocked space.
+    pub blocking: bool,
+    // Additional pathfinding cost for entering this space.
+    pub cost: u32,
+    // Elevation of the terrain in this space.
+    pub height: i32,
 }
 
 impl Default for Space {
@@ -25,6 +42,10 @@ impl Default for Space {
         Space {
             unit: None,
             tile: None,
+            object: None,
+            blocking: false,
+            cost: 0,
+            height: 0,
         }
     }
 }
@@ -51,6 +72,92 @@ impl Grid {
         self.grid.size().x * self.grid.size().y
     }
 
+    pub fn space(&self, location: &IVec2) -> Option<&Space> {
+        self.grid.get(location)
+    }
+
+    // Marks the space at the given location as blocking or not.
+    pub fn set_blocking(&mut self, location: &IVec2, blocking: bool) {
+        if let Some(space) = self.grid.get_mut(location) {
+            space.blocking = blocking;
+        }
+    }
+
+    // Sets the additional pathfinding cost for entering the space at the given location.
+    pub fn set_cost(&mut self, location: &IVec2, cost: u32) {
+        if let Some(space) = self.grid.get_mut(location) {
+            space.cost = cost;
+        }
+    }
+
+    // Returns the elevation of the terrain at the given location.
+    pub fn height(&self, location: &IVec2) -> i32 {
+        self.grid.get(location).map_or(0, |space| space.height)
+    }
+
+    // Sets the elevation of the terrain at the given location.
+    pub fn set_height(&mut self, location: &IVec2, height: i32) {
+        if let Some(space) = self.grid.get_mut(location) {
+            space.height = height;
+        }
+    }
+
+    // Returns the extra movement spent climbing from one location to a neighbouring one, if the climb is possible.
+    pub fn climb_cost(&self, from: &IVec2, to: &IVec2) -> Option<u32> {
+        let climb = self.height(to) - self.height(from);
+        (climb <= MAX_CLIMB).then_some(climb.max(0) as u32)
+    }
+
+    // Returns the extra attack range from attacking down onto a lower location.
+    pub fn range_bonus(&self, from: &IVec2, to: &IVec2) -> f32 {
+        (self.height(from) - self.height(to)).max(0) as f32 * HIGH_GROUND_RANGE
+    }
+
+    // Returns true if no space between the two locations rises above both of them.
+    pub fn line_of_sight(&self, from: &IVec2, to: &IVec2) -> bool {
+        let eye = self.height(from).max(self.height(to));
+        let line = cords::line(from, to);
+        line.iter()
+            .skip(1)
+            .take(line.len().saturating_sub(2))
+            .all(|location| self.height(location) <= eye)
+    }
+
+    // Returns true if an attack with the given range reaches from one location to another.
+    // Attacking from high ground reaches further, but taller terrain in between blocks the attack.
+    pub fn in_range(&self, from: &IVec2, to: &IVec2, range: f32) -> bool {
+        let range = range + self.range_bonus(from, to);
+        to.as_vec2().distance_squared(from.as_vec2()) <= range * range
+            && self.line_of_sight(from, to)
+    }
+
+    // Iterates every location an attack with the given range reaches from a location.
+    pub fn attackable(&self, from: &IVec2, range: f32) -> impl Iterator<Item = IVec2> {
+        let reach = range + self.height(from).max(0) as f32 * HIGH_GROUND_RANGE;
+        let from = *from;
+        selection::Shape::Circle(from.as_vec2(), reach)
+            .cells(self.size())
+            .filter(move |location| *location != from && self.in_range(&from, location, range))
+    }
+
+    // Returns true if an entity of a specific kind can enter the given location.
+    pub fn can_enter(&self, kind: &EntityKind, location: &IVec2) -> bool {
+        self.grid
+            .get(location)
+            .is_some_and(|space| kind.can_enter(space))
+    }
+
+    // Iterates all entities of a specific kind within the selection shape.
+    pub fn entities_within(
+        &self,
+        kind: &EntityKind,
+        selection: selection::Shape,
+    ) -> impl Iterator<Item = (IVec2, Entity)> {
+        selection
+            .cells(self.size())
+            .filter_map(move |location| self.get_entity(kind, &location).map(|e| (location, e)))
+    }
+
     // Gets an entity of a specific kind at the given location.
     pub fn get_entity(&self, kind: &EntityKind, location: &IVec2) -> Option<Entity> {
         self.grid.get(location).and_then(|space| kind.get(space))
@@ -80,7 +187,7 @@ impl Grid {
         bundle: impl Bundle,
     ) -> Option<Entity> {
         if let Some(space) = self.grid.get_mut(location) {
-            if kind.get(space).is_none() {
+            if kind.can_enter(space) {
                 let entity = commands
                     .spawn((
                         GridLocation::new(location.clone(), kind.clone()),
@@ -95,9 +202,18 @@ impl Grid {
         None
     }
 
+    // Places an existing entity into the space at its location if the space is empty, such as one loaded from a save. Returns true if successful.
+    pub fn place(&mut self, location: &GridLocation, entity: Entity) -> bool {
+        if self.can_enter(&location.kind, &location.location) {
+            self.set_entity(&location.kind, &location.location, entity);
+            return true;
+        }
+        false
+    }
+
     // Moves an entity of a specific kind from one location to another if the target location is empty. Returns the moved entity if successful.
     pub fn move_to(&mut self, from: &mut GridLocation, to: &IVec2) -> Option<Entity> {
-        if self.get_entity(&from.kind, to).is_none() {
+        if self.can_enter(&from.kind, to) {
             if let Some(entity) = self.take_entity(&from.kind, &from.location) {
                 self.set_entity(&from.kind, to, entity);
                 from.location = to.clone();
@@ -107,6 +223,48 @@ impl Grid {
         None
     }
 
+    // Slides an entity of a specific kind in a direction for up to a distance, stopping before any space it can not enter.
+    // Returns the path including the starting location, and the location collided with if the slide was cut short.
+    pub fn slide(
+        &self,
+        kind: &EntityKind,
+        from: &IVec2,
+        direction: &IVec2,
+        distance: u32,
+    ) -> (Vec<IVec2>, Option<IVec2>) {
+        let mut path = vec![*from];
+        if *direction == IVec2::ZERO {
+            return (path, None);
+        }
+        let mut location = *from;
+        for _ in 0..distance {
+            let next = location + direction;
+            if !self.can_enter(kind, &next) {
+                return (path, Some(next));
+            }
+            path.push(next);
+            location = next;
+        }
+        (path, None)
+    }
+
+    // Swaps the locations of two entities of the same kind. Returns true if successful.
+    pub fn swap(&mut self, a: &mut GridLocation, b: &mut GridLocation) -> bool {
+        if a.kind != b.kind {
+            return false;
+        }
+        let (Some(first), Some(second)) = (
+            self.get_entity(&a.kind, &a.location),
+            self.get_entity(&b.kind, &b.location),
+        ) else {
+            return false;
+        };
+        self.set_entity(&a.kind, &b.location, first);
+        self.set_entity(&b.kind, &a.location, second);
+        std::mem::swap(&mut a.location, &mut b.location);
+        true
+    }
+
     // finds the nearest entity of a specific kind from a starting location in a given direction and selection shape that satisfies a predicate.
     pub fn nearest_entity(
         &self,
@@ -127,20 +285,6 @@ impl Grid {
             })
     }
 
-    // finds the nearest empty location for a specific entity kind from a starting location in a given direction and selection shape.
-    pub fn nearest_empty(
-        &self,
-        kind: &EntityKind,
-        location: &IVec2,
-        direction: &IVec2,
-        selection: selection::Shape,
-    ) -> Option<IVec2> {
-        self.grid
-            .iter_breath(location.clone(), direction.clone(), selection)
-            .skip(1)
-            .find(|location| self.get_entity(kind, location).is_none())
-    }
-
     // A* pathfinding algorithm to find a path from start to end for a specific entity kind.
     pub fn a_star_to(
         &self,
@@ -150,21 +294,94 @@ impl Grid {
         steps: usize,
     ) -> Vec<IVec2> {
         self.grid
-            .a_star(from, to, |space| kind.get(space).is_none())
+            .a_star(from, to, |space| kind.can_enter(space))
             .into_iter()
             .take(steps + 1)
             .collect()
     }
 
+    // A* pathfinding algorithm like `a_star_to` that prefers routes through spaces with less extra cost.
+    // Leaving some locations can cost extra movement, and the path ends once the movement is spent.
+    pub fn a_star_weighted_to(
+        &self,
+        kind: &EntityKind,
+        from: &IVec2,
+        to: &IVec2,
+        movement: u32,
+        leave_cost: impl Fn(&IVec2) -> u32,
+    ) -> Vec<IVec2> {
+        let path = self.grid.a_star_weighted(from, to, |previous, space| {
+            let climb = space.height - self.height(previous);
+            (kind.can_enter(space) && climb <= MAX_CLIMB)
+                .then(|| 1 + space.cost + climb.max(0) as u32 + leave_cost(previous))
+        });
+        let mut spent = 0;
+        let mut steps: Vec<IVec2> = Vec::with_capacity(path.len());
+        for location in path {
+            if let Some(previous) = steps.last() {
+                spent +=
+                    1 + leave_cost(previous) + self.climb_cost(previous, &location).unwrap_or(0);
+                if spent > movement {
+                    break;
+                }
+            }
+            steps.push(location);
+        }
+        steps
+    }
+
+    // Finds every location an entity of a specific kind could move to with the given movement.
+    // Movement is spent the same way as in `a_star_weighted_to`, and the start is always reachable.
+    pub fn reachable(
+        &self,
+        kind: &EntityKind,
+        from: &IVec2,
+        movement: u32,
+        leave_cost: impl Fn(&IVec2) -> u32,
+    ) -> Vec<IVec2> {
+        let size = self.size();
+        let mut spent = vec![u32::MAX; self.spaces() as usize];
+        let mut queue = BinaryHeap::new();
+        if cords::location_within(&IVec2::ZERO, &size, from) {
+            spent[cords::location_to_index(&size, from)] = 0;
+            queue.push(Reverse((0, from.x, from.y)));
+        }
+        let mut reached = Vec::new();
+        while let Some(Reverse((cost, x, y))) = queue.pop() {
+            let location = IVec2::new(x, y);
+            if cost > spent[cords::location_to_index(&size, &location)] {
+                continue;
+            }
+            reached.push(location);
+            for offset in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+                let next = location + offset;
+                let (Some(climb), true) = (
+                    self.climb_cost(&location, &next),
+                    self.can_enter(kind, &next),
+                ) else {
+                    continue;
+                };
+                let total = cost + 1 + leave_cost(&location) + climb;
+                let index = cords::location_to_index(&size, &next);
+                if total <= movement && total < spent[index] {
+                    spent[index] = total;
+                    queue.push(Reverse((total, next.x, next.y)));
+                }
+            }
+        }
+        reached
+    }
+
     // A* pathfinding algorithm to find a path from start to end, stopping next to the target.
     pub fn a_star_next_to(
         &self,
         kind: &EntityKind,
         from: &IVec2,
         to: &IVec2,
-        steps: usize,
+        movement: u32,
+        leave_cost: impl Fn(&IVec2) -> u32,
     ) -> Vec<IVec2> {
-        let mut path = self.a_star_to(kind, from, to, steps);
+        let mut path = self.a_star_weighted_to(kind, from, to, movement, leave_cost);
         if let Some(last) = path.last() {
             if last == to {
                 path.pop();
@@ -172,13 +389,22 @@ impl Grid {
         }
         path
     }
+
+    // Finds the first blocked space on the shortest path between two locations if blocked spaces could be entered.
+    pub fn first_blocking(&self, kind: &EntityKind, from: &IVec2, to: &IVec2) -> Option<IVec2> {
+        self.grid
+            .a_star(from, to, |space| space.blocking || kind.can_enter(space))
+            .into_iter()
+            .find(|location| self.grid.get(location).is_some_and(|space| space.blocking))
+    }
 }
 
-#[derive(Clone, Copy, Debug, Reflect)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
 // Represents the type of entity that can occupy a space in the grid.
 pub enum EntityKind {
     Unit,
     Tile,
+    Object,
 }
 
 impl EntityKind {
@@ -186,6 +412,7 @@ impl EntityKind {
         match self {
             EntityKind::Unit => space.unit = Some(entity),
             EntityKind::Tile => space.tile = Some(entity),
+            EntityKind::Object => space.object = Some(entity),
         }
     }
 
@@ -193,18 +420,34 @@ impl EntityKind {
         match self {
             EntityKind::Unit => space.unit,
             EntityKind::Tile => space.tile,
+            EntityKind::Object => space.object,
         }
     }
 
+    // Removing an object also removes any blocking or cost it applied to the space.
     pub fn take(&self, space: &mut Space) -> Option<Entity> {
         match self {
             EntityKind::Unit => space.unit.take(),
             EntityKind::Tile => space.tile.take(),
+            EntityKind::Object => {
+                space.blocking = false;
+                space.cost = 0;
+                space.object.take()
+            }
+        }
+    }
+
+    // Returns true if this kind of entity can occupy the space.
+    pub fn can_enter(&self, space: &Space) -> bool {
+        match self {
+            EntityKind::Unit => space.unit.is_none() && !space.blocking,
+            _ => self.get(space).is_none(),
         }
     }
 }
 
 #[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
 #[require(Transform)]
 pub struct GridLocation {
     location: IVec2,
@@ -241,24 +484,54 @@ impl GridLocation {
 #[require(Transform)]
 pub struct GridScale {
     scale: IVec2,
+    // Vertical offset for each level of elevation.
+    elevation: i32,
 }
 
 impl GridScale {
     pub fn new(scale: IVec2) -> Self {
-        GridScale { scale }
+        GridScale {
+            scale,
+            elevation: scale.y / 4,
+        }
     }
 
     pub fn scale(&self) -> &IVec2 {
         &self.scale
     }
 
-    pub fn iter_in_scale(
-        &self,
+    // Converts a grid location to a world position, raised by the elevation of the terrain.
+    // Higher terrain is drawn slightly above lower terrain on the same layer.
+    pub fn translation(&self, grid: &Grid, location: &IVec2, z: i32) -> Vec3 {
+        let height = grid.height(location);
+        cords::location_to_translation(location, &self.scale, z)
+            + Vec3::new(0.0, (height * self.elevation) as f32, height as f32 * 0.01)
+    }
+
+    // Converts a world position to the grid location drawn there, allowing for raised terrain.
+    // Raised spaces are drawn higher up the column, and over lower spaces where they overlap.
+    pub fn location(&self, grid: &Grid, translation: &Vec2) -> Option<IVec2> {
+        let flat = cords::translation_to_location(translation, &self.scale);
+        let half = self.scale.as_vec2() / 2.0;
+        (0..=flat.y.min(grid.size().y - 1))
+            .rev()
+            .map(|y| IVec2::new(flat.x, y))
+            .filter(|location| cords::location_within(&IVec2::ZERO, &grid.size(), location))
+            .filter(|location| {
+                let center = self.translation(grid, location, 0).truncate();
+                (translation - center).abs().cmple(half).all()
+            })
+            .max_by_key(|location| (grid.height(location), location.y))
+    }
+
+    // Converts grid locations to world positions around the origin, raised by the elevation of the terrain.
+    pub fn iter_in_scale<'a>(
+        &'a self,
+        grid: &'a Grid,
         origin: Vec2,
-        iter: impl Iterator<Item = IVec2>,
-    ) -> impl Iterator<Item = Vec2> {
-        let scale = self.scale.clone();
-        iter.map(move |location| (location * scale).as_vec2() + origin)
+        iter: impl Iterator<Item = IVec2> + 'a,
+    ) -> impl Iterator<Item = Vec2> + 'a {
+        iter.map(move |location| self.translation(grid, &location, 0).xy() + origin)
     }
 }
 
@@ -266,6 +539,12 @@ impl GridScale {
 #[relationship(relationship_target = GridOwned)]
 pub struct GridOwner(Entity);
 
+impl GridOwner {
+    pub fn new(grid_entity: Entity) -> Self {
+        GridOwner(grid_entity)
+    }
+}
+
 #[derive(Component, Debug, Reflect)]
 #[relationship_target(relationship = GridOwner)]
 pub struct GridOwned(Vec<Entity>);
@@ -407,6 +686,259 @@ mod tests {
         assert!(path.iter().all(|p| !(p.x == 2 && p.y >= 1)));
     }
 
+    #[test]
+    fn test_a_star_to_blocked_by_blocking_spaces() {
+        let mut grid = Grid::new(IVec2::new(5, 5));
+
+        // Block a vertical line at x=2
+        for y in 0..5 {
+            grid.set_blocking(&IVec2::new(2, y), true);
+        }
+
+        let path = grid.a_star_to(&EntityKind::Unit, &IVec2::new(0, 2), &IVec2::new(4, 2), 100);
+
+        assert!(path.is_empty());
+    }
+
+    #[test]
+    fn test_a_star_weighted_to_avoids_costly_spaces() {
+        let mut grid = Grid::new(IVec2::new(5, 5));
+
+        // Make the direct route expensive, leaving cheap spaces at y=0
+        for y in 1..5 {
+            grid.set_cost(&IVec2::new(2, y), 10);
+        }
+
+        let path = grid.a_star_weighted_to(
+            &EntityKind::Unit,
+            &IVec2::new(0, 2),
+            &IVec2::new(4, 2),
+            100,
+            |_| 0,
+        );
+
+        assert_eq!(path.last(), Some(&IVec2::new(4, 2)));
+        assert!(path.iter().all(|p| !(p.x == 2 && p.y >= 1)));
+    }
+
+    #[test]
+    fn test_take_object_clears_blocking() {
+        let mut grid = Grid::new(IVec2::new(5, 5));
+        let object = Entity::from_bits(1);
+        let location = IVec2::new(2, 2);
+
+        grid.set_entity(&EntityKind::Object, &location, object);
+        grid.set_blocking(&location, true);
+        grid.set_cost(&location, 3);
+        assert!(!grid.can_enter(&EntityKind::Unit, &location));
+
+        assert_eq!(
+            grid.take_entity(&EntityKind::Object, &location),
+            Some(object)
+        );
+        assert!(grid.can_enter(&EntityKind::Unit, &location));
+        assert_eq!(grid.grid.get(&location).map(|s| s.cost), Some(0));
+    }
+
+    #[test]
+    fn test_first_blocking() {
+        let mut grid = Grid::new(IVec2::new(5, 5));
+        let from = IVec2::new(0, 2);
+        let to = IVec2::new(4, 2);
+        assert_eq!(grid.first_blocking(&EntityKind::Unit, &from, &to), None);
+
+        // A wall across the whole grid leaves no way around it.
+        for y in 0..5 {
+            grid.set_blocking(&IVec2::new(2, y), true);
+        }
+        assert!(grid.a_star_to(&EntityKind::Unit, &from, &to, 10).is_empty());
+        assert_eq!(
+            grid.first_blocking(&EntityKind::Unit, &from, &to),
+            Some(IVec2::new(2, 2))
+        );
+    }
+
+    #[test]
+    fn test_slide_stops_before_blocker() {
+        let mut grid = Grid::new(IVec2::new(5, 5));
+        let blocker = Entity::from_bits(1);
+        grid.set_entity(&EntityKind::Unit, &IVec2::new(3, 2), blocker);
+
+        let (path, collision) =
+            grid.slide(&EntityKind::Unit, &IVec2::new(0, 2), &IVec2::new(1, 0), 5);
+
+        assert_eq!(
+            path,
+            vec![IVec2::new(0, 2), IVec2::new(1, 2), IVec2::new(2, 2)]
+        );
+        assert_eq!(collision, Some(IVec2::new(3, 2)));
+    }
+
+    #[test]
+    fn test_slide_stops_at_grid_edge() {
+        let grid = Grid::new(IVec2::new(5, 5));
+
+        let (path, collision) =
+            grid.slide(&EntityKind::Unit, &IVec2::new(3, 2), &IVec2::new(1, 0), 3);
+
+        assert_eq!(path, vec![IVec2::new(3, 2), IVec2::new(4, 2)]);
+        assert_eq!(collision, Some(IVec2::new(5, 2)));
+    }
+
+    #[test]
+    fn test_slide_full_distance() {
+        let grid = Grid::new(IVec2::new(5, 5));
+
+        let (path, collision) =
+            grid.slide(&EntityKind::Unit, &IVec2::new(0, 0), &IVec2::new(1, 1), 2);
+
+        assert_eq!(path.last(), Some(&IVec2::new(2, 2)));
+        assert_eq!(path.len(), 3);
+        assert_eq!(collision, None);
+    }
+
+    #[test]
+    fn test_swap() {
+        let mut grid = Grid::new(IVec2::new(5, 5));
+        let first = Entity::from_bits(1);
+        let second = Entity::from_bits(2);
+        let mut a = GridLocation::new(IVec2::new(0, 0), EntityKind::Unit);
+        let mut b = GridLocation::new(IVec2::new(4, 4), EntityKind::Unit);
+        grid.set_entity(&EntityKind::Unit, a.location(), first);
+        grid.set_entity(&EntityKind::Unit, b.location(), second);
+
+        assert!(grid.swap(&mut a, &mut b));
+        assert_eq!(a.location(), &IVec2::new(4, 4));
+        assert_eq!(b.location(), &IVec2::new(0, 0));
+        assert_eq!(
+            grid.get_entity(&EntityKind::Unit, &IVec2::new(4, 4)),
+            Some(first)
+        );
+        assert_eq!(
+            grid.get_entity(&EntityKind::Unit, &IVec2::new(0, 0)),
+            Some(second)
+        );
+    }
+
+    #[test]
+    fn test_a_star_weighted_to_with_leave_cost() {
+        let grid = Grid::new(IVec2::new(5, 5));
+        let start = IVec2::new(0, 0);
+        let path = grid.a_star_weighted_to(
+            &EntityKind::Unit,
+            &start,
+            &IVec2::new(4, 0),
+            3,
+            |location| {
+                if *location == start { 1 } else { 0 }
+            },
+        );
+
+        // Leaving the start spends 2 movement, leaving 1 more step
+        assert_eq!(path, vec![start, IVec2::new(1, 0), IVec2::new(2, 0)]);
+    }
+
+    #[test]
+    fn test_reachable() {
+        let mut grid = Grid::new(IVec2::new(5, 5));
+        grid.set_blocking(&IVec2::new(1, 0), true);
+        grid.set_height(&IVec2::new(0, 1), 1);
+
+        let mut reached = grid.reachable(&EntityKind::Unit, &IVec2::new(0, 0), 2, |_| 0);
+        reached.sort_by_key(|location| (location.x, location.y));
+
+        // Climbing onto the hill spends both points of movement, and the blocked space can not be entered.
+        assert_eq!(reached, vec![IVec2::new(0, 0), IVec2::new(0, 1)]);
+    }
+
+    #[test]
+    fn test_reachable_with_leave_cost() {
+        let grid = Grid::new(IVec2::new(5, 1));
+        let start = IVec2::new(2, 0);
+        let mut reached = grid.reachable(&EntityKind::Unit, &start, 2, |location| {
+            if *location == start { 1 } else { 0 }
+        });
+        reached.sort_by_key(|location| location.x);
+
+        assert_eq!(
+            reached,
+            vec![IVec2::new(1, 0), IVec2::new(2, 0), IVec2::new(3, 0)]
+        );
+    }
+
+    #[test]
+    fn test_a_star_weighted_to_climbing_costs_movement() {
+        let mut grid = Grid::new(IVec2::new(5, 1));
+        grid.set_height(&IVec2::new(1, 0), 2);
+
+        let path = grid.a_star_weighted_to(
+            &EntityKind::Unit,
+            &IVec2::new(0, 0),
+            &IVec2::new(4, 0),
+            3,
+            |_| 0,
+        );
+
+        // Climbing 2 levels spends 3 movement on the first step
+        assert_eq!(path, vec![IVec2::new(0, 0), IVec2::new(1, 0)]);
+    }
+
+    #[test]
+    fn test_a_star_weighted_to_cliffs_block() {
+        let mut grid = Grid::new(IVec2::new(5, 5));
+        for y in 0..5 {
+            grid.set_height(&IVec2::new(2, y), MAX_CLIMB + 1);
+        }
+
+        let path = grid.a_star_weighted_to(
+            &EntityKind::Unit,
+            &IVec2::new(0, 2),
+            &IVec2::new(4, 2),
+            100,
+            |_| 0,
+        );
+
+        assert!(path.is_empty());
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        let mut grid = Grid::new(IVec2::new(5, 5));
+        grid.set_height(&IVec2::new(2, 2), 2);
+
+        assert!(!grid.line_of_sight(&IVec2::new(0, 2), &IVec2::new(4, 2)));
+        assert!(grid.line_of_sight(&IVec2::new(0, 0), &IVec2::new(4, 0)));
+
+        // Looking down from equal height sees over the rise
+        grid.set_height(&IVec2::new(0, 2), 2);
+        assert!(grid.line_of_sight(&IVec2::new(0, 2), &IVec2::new(4, 2)));
+    }
+
+    #[test]
+    fn test_attackable() {
+        let mut grid = Grid::new(IVec2::new(7, 7));
+        let center = IVec2::new(3, 3);
+        assert_eq!(grid.attackable(&center, 1.0).count(), 4);
+        assert_eq!(grid.attackable(&center, 1.5).count(), 8);
+
+        // High ground reaches one space further.
+        grid.set_height(&center, 1);
+        assert!(grid.attackable(&center, 1.0).any(|l| l == IVec2::new(3, 5)));
+        assert!(!grid.attackable(&center, 1.0).any(|l| l == center));
+    }
+
+    #[test]
+    fn test_range_bonus() {
+        let mut grid = Grid::new(IVec2::new(5, 5));
+        grid.set_height(&IVec2::new(0, 0), 2);
+
+        assert_eq!(
+            grid.range_bonus(&IVec2::new(0, 0), &IVec2::new(4, 4)),
+            2.0 * HIGH_GROUND_RANGE
+        );
+        assert_eq!(grid.range_bonus(&IVec2::new(4, 4), &IVec2::new(0, 0)), 0.0);
+    }
+
     #[test]
     fn test_a_star_to_zero_steps() {
         let grid = Grid::new(IVec2::new(5, 5));
@@ -431,4 +963,26 @@ mod tests {
         assert_eq!(path[0], IVec2::new(2, 2));
         assert_eq!(path[1], IVec2::new(2, 3));
     }
+
+    #[test]
+    fn test_scale_location() {
+        let mut grid = Grid::new(IVec2::new(5, 5));
+        let scale = GridScale::new(IVec2::new(16, 16));
+        assert_eq!(
+            scale.location(&grid, &Vec2::new(0.0, 0.0)),
+            Some(IVec2::ZERO)
+        );
+        assert_eq!(
+            scale.location(&grid, &Vec2::new(39.0, 25.0)),
+            Some(IVec2::new(2, 2))
+        );
+        assert_eq!(scale.location(&grid, &Vec2::new(-20.0, -20.0)), None);
+
+        // A raised space is drawn over the flat space above it.
+        grid.set_height(&IVec2::new(2, 1), 4);
+        assert_eq!(
+            scale.location(&grid, &Vec2::new(32.0, 32.0)),
+            Some(IVec2::new(2, 1))
+        );
+    }
 }
@@ -1,13 +1,56 @@
 use bevy::prelude::*;
 
-use crate::random::RandomSource;
+use crate::random::RandomStream;
+use crate::util::cords;
+
+// Cells lying exactly on the edge of a cone are included despite rounding errors.
+const ANGLE_TOLERANCE: f32 = 0.0001;
 
 #[derive(Clone, Debug, Reflect)]
 // Defines a subsection of a grid to iterate over. Used to optimize search areas.
 pub enum Shape {
     All,
     Circle(Vec2, f32),
+    // Every location from the start up to but not including the end.
     Square(IVec2, IVec2),
+    // Locations within a number of steps of the center, not counting diagonals.
+    Diamond(IVec2, i32),
+    // Locations between the inner and outer radius of the center.
+    Ring(Vec2, f32, f32),
+    // Locations on a straight line between two locations, including both ends.
+    Line(IVec2, IVec2),
+    // Locations within the range of the origin that are no more than the angle in degrees to either side of the direction.
+    // The origin itself is not included.
+    Cone(IVec2, IVec2, f32, f32),
+    // Locations within the extent of the center in each direction.
+    Rect(IVec2, IVec2),
+    // Locations in any of the shapes.
+    Union(Vec<Shape>),
+    // Locations in every one of the shapes.
+    Intersection(Vec<Shape>),
+    // Locations in the first shape but none of the others.
+    Difference(Vec<Shape>),
+}
+
+#[derive(Clone, Debug, Reflect)]
+// Strategies for choosing several spaces within a shape at once.
+pub enum Placement {
+    // Random spaces that are at least the given distance apart where possible.
+    Poisson(f32),
+    // Divides the shape into even cells and picks a random space in each, spreading spaces across the shape.
+    Jittered,
+    // A formation centered on the shape, facing the given direction.
+    Formation(Formation, IVec2),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum Formation {
+    // Side by side across the facing direction.
+    Line,
+    // A leader with the rest spreading out diagonally behind.
+    Wedge,
+    // Rows as wide as they are deep.
+    Block,
 }
 
 impl Shape {
@@ -23,12 +66,127 @@ impl Shape {
                     && location.y >= start.y
                     && location.y < end.y
             }
+            Shape::Diamond(center, radius) => (location - center).abs().element_sum() <= *radius,
+            Shape::Ring(center, inner, outer) => {
+                let distance = center.distance_squared(location.as_vec2());
+                distance >= inner * inner && distance <= outer * outer
+            }
+            Shape::Line(from, to) => {
+                let (start, end) = (from.min(*to), from.max(*to) + IVec2::ONE);
+                cords::location_within(&start, &end, location)
+                    && cords::line(from, to).contains(location)
+            }
+            Shape::Cone(origin, direction, range, angle) => {
+                let offset = (location - origin).as_vec2();
+                offset != Vec2::ZERO
+                    && offset.length_squared() <= range * range
+                    && direction.as_vec2().angle_to(offset).abs()
+                        <= angle.to_radians() + ANGLE_TOLERANCE
+            }
+            Shape::Rect(center, extent) => (location - center).abs().cmple(*extent).all(),
+            Shape::Union(shapes) => shapes.iter().any(|shape| shape.contains(location)),
+            Shape::Intersection(shapes) => shapes.iter().all(|shape| shape.contains(location)),
+            Shape::Difference(shapes) => shapes.split_first().is_some_and(|(first, rest)| {
+                first.contains(location) && !rest.iter().any(|shape| shape.contains(location))
+            }),
+        }
+    }
+
+    // Moves the shape by an offset. All has no position and is unchanged.
+    pub fn translate(self, offset: IVec2) -> Shape {
+        let shift = offset.as_vec2();
+        let translate_all =
+            |shapes: Vec<Shape>| shapes.into_iter().map(|s| s.translate(offset)).collect();
+        match self {
+            Shape::All => Shape::All,
+            Shape::Circle(center, radius) => Shape::Circle(center + shift, radius),
+            Shape::Square(start, end) => Shape::Square(start + offset, end + offset),
+            Shape::Diamond(center, radius) => Shape::Diamond(center + offset, radius),
+            Shape::Ring(center, inner, outer) => Shape::Ring(center + shift, inner, outer),
+            Shape::Line(from, to) => Shape::Line(from + offset, to + offset),
+            Shape::Cone(origin, direction, range, angle) => {
+                Shape::Cone(origin + offset, direction, range, angle)
+            }
+            Shape::Rect(center, extent) => Shape::Rect(center + offset, extent),
+            Shape::Union(shapes) => Shape::Union(translate_all(shapes)),
+            Shape::Intersection(shapes) => Shape::Intersection(translate_all(shapes)),
+            Shape::Difference(shapes) => Shape::Difference(translate_all(shapes)),
+        }
+    }
+
+    // Returns the start and end of a box containing the shape, which may reach outside a grid of the given size.
+    fn extent(&self, size: IVec2) -> (IVec2, IVec2) {
+        let around = |center: &Vec2, radius: f32| {
+            (
+                (center - radius).floor().as_ivec2(),
+                (center + radius).floor().as_ivec2() + IVec2::ONE,
+            )
+        };
+        match self {
+            Shape::All => (IVec2::ZERO, size),
+            Shape::Circle(center, radius) => around(center, *radius),
+            Shape::Square(start, end) => (*start, *end),
+            Shape::Diamond(center, radius) => (center - radius, center + radius + 1),
+            Shape::Ring(center, _, outer) => around(center, *outer),
+            Shape::Line(from, to) => (from.min(*to), from.max(*to) + IVec2::ONE),
+            Shape::Cone(origin, _, range, _) => around(&origin.as_vec2(), *range),
+            Shape::Rect(center, extent) => (center - extent, center + extent + IVec2::ONE),
+            Shape::Union(shapes) => shapes
+                .iter()
+                .map(|shape| shape.extent(size))
+                .reduce(|(a_start, a_end), (b_start, b_end)| {
+                    (a_start.min(b_start), a_end.max(b_end))
+                })
+                .unwrap_or((IVec2::ZERO, IVec2::ZERO)),
+            Shape::Intersection(shapes) => shapes
+                .iter()
+                .map(|shape| shape.extent(size))
+                .reduce(|(a_start, a_end), (b_start, b_end)| {
+                    (a_start.max(b_start), a_end.min(b_end))
+                })
+                .unwrap_or((IVec2::ZERO, size)),
+            Shape::Difference(shapes) => shapes
+                .first()
+                .map_or((IVec2::ZERO, IVec2::ZERO), |shape| shape.extent(size)),
+        }
+    }
+
+    // Returns the start and end of the box surrounding the shape, limited to a grid of the given size.
+    fn bounds(&self, size: IVec2) -> (IVec2, IVec2) {
+        let (start, end) = self.extent(size);
+        (start.max(IVec2::ZERO), end.min(size))
+    }
+
+    // Iterates the locations within the shape on a grid of the given size, without visiting the rest of the grid.
+    pub fn cells(&self, size: IVec2) -> impl Iterator<Item = IVec2> + use<> {
+        let (start, end) = self.bounds(size);
+        let shape = self.clone();
+        (start.y..end.y)
+            .flat_map(move |y| (start.x..end.x).map(move |x| IVec2::new(x, y)))
+            .filter(move |location| shape.contains(location))
+    }
+
+    // Returns the center of the shape on a grid of the given size.
+    fn center(&self, size: IVec2) -> IVec2 {
+        match self {
+            Shape::Circle(center, _) | Shape::Ring(center, _, _) => center.round().as_ivec2(),
+            Shape::Diamond(center, _) | Shape::Rect(center, _) | Shape::Cone(center, _, _, _) => {
+                *center
+            }
+            _ => {
+                let (start, end) = self.bounds(size);
+                (start + end - IVec2::ONE) / 2
+            }
         }
     }
 
-    pub fn random(&self, source: &mut RandomSource) -> IVec2 {
+    // Returns a random location within the shape on a grid of the given size.
+    pub fn random(&self, source: &mut RandomStream, size: IVec2) -> IVec2 {
         match self {
-            Shape::All => IVec2::ZERO,
+            Shape::All => IVec2::new(
+                source.random::<i32>().rem_euclid(size.x),
+                source.random::<i32>().rem_euclid(size.y),
+            ),
             Shape::Circle(center, radius) => {
                 let angle = source.random::<f32>() * std::f32::consts::TAU;
                 let r = source.random::<f32>() * radius;
@@ -41,6 +199,368 @@ impl Shape {
                 source.random::<i32>().rem_euclid(end.x - start.x) + start.x,
                 source.random::<i32>().rem_euclid(end.y - start.y) + start.y,
             ),
+            // Other shapes choose from their cells, falling back to the center if they have none.
+            _ => {
+                let cells: Vec<IVec2> = self.cells(size).collect();
+                source
+                    .choose(&cells)
+                    .copied()
+                    .unwrap_or_else(|| self.center(size))
+            }
+        }
+    }
+
+    // Chooses up to the amount of distinct free locations within the shape on a grid of the given size.
+    // Fewer locations are returned only when the shape does not have enough free spaces.
+    pub fn place(
+        &self,
+        source: &mut RandomStream,
+        size: IVec2,
+        amount: usize,
+        placement: &Placement,
+        free: impl Fn(&IVec2) -> bool,
+    ) -> Vec<IVec2> {
+        let mut candidates: Vec<IVec2> = self.cells(size).filter(|l| free(l)).collect();
+        source.shuffle(&mut candidates);
+        let mut placed = match placement {
+            Placement::Poisson(distance) => poisson(&candidates, amount, *distance),
+            Placement::Jittered => jittered(&candidates, amount, self.bounds(size)),
+            Placement::Formation(formation, facing) => {
+                let anchor = self.center(size);
+                formation
+                    .offsets(amount, facing)
+                    .into_iter()
+                    .filter_map(|offset| {
+                        nearest(&candidates, &(anchor + offset))
+                            .map(|index| candidates.remove(index))
+                    })
+                    .collect()
+            }
+        };
+        // Fill any spaces the strategy could not place from the remaining candidates.
+        for candidate in candidates {
+            if placed.len() >= amount {
+                break;
+            }
+            if !placed.contains(&candidate) {
+                placed.push(candidate);
+            }
+        }
+        placed.truncate(amount);
+        placed
+    }
+}
+
+impl Formation {
+    // Returns the offset of each member from the front center of the formation.
+    pub fn offsets(&self, amount: usize, facing: &IVec2) -> Vec<IVec2> {
+        let forward = if *facing == IVec2::ZERO {
+            IVec2::Y
+        } else {
+            facing.signum()
+        };
+        let across = IVec2::new(-forward.y, forward.x);
+        // Alternates either side of the center: 0, 1, -1, 2, -2, ...
+        let side = |index: usize| {
+            let step = index.div_ceil(2) as i32;
+            if index % 2 == 1 { step } else { -step }
+        };
+        match self {
+            Formation::Line => (0..amount).map(|i| across * side(i)).collect(),
+            Formation::Wedge => (0..amount)
+                .map(|i| {
+                    let row = i.div_ceil(2) as i32;
+                    -forward * row + across * side(i)
+                })
+                .collect(),
+            Formation::Block => {
+                let width = (amount as f32).sqrt().ceil().max(1.0) as usize;
+                (0..amount)
+                    .map(|i| -forward * (i / width) as i32 + across * side(i % width))
+                    .collect()
+            }
+        }
+    }
+}
+
+// Greedily accepts candidates that are far enough from every accepted location.
+fn poisson(candidates: &[IVec2], amount: usize, distance: f32) -> Vec<IVec2> {
+    let mut placed: Vec<IVec2> = Vec::with_capacity(amount);
+    for candidate in candidates {
+        if placed.len() >= amount {
+            break;
+        }
+        if placed
+            .iter()
+            .all(|p| p.as_vec2().distance_squared(candidate.as_vec2()) >= distance * distance)
+        {
+            placed.push(*candidate);
+        }
+    }
+    placed
+}
+
+// Takes the first candidate in each cell of an even grid laid over the bounds.
+fn jittered(candidates: &[IVec2], amount: usize, bounds: (IVec2, IVec2)) -> Vec<IVec2> {
+    let (start, end) = bounds;
+    let extent = (end - start).max(IVec2::ONE).as_vec2();
+    let columns = ((amount as f32 * extent.x / extent.y).sqrt().ceil() as i32).max(1);
+    let rows = ((amount as i32 + columns - 1) / columns).max(1);
+    let cell_size = extent / IVec2::new(columns, rows).as_vec2();
+    let mut filled = vec![false; (columns * rows) as usize];
+    let mut placed = Vec::with_capacity(amount);
+    for candidate in candidates {
+        let cell = ((candidate - start).as_vec2() / cell_size)
+            .as_ivec2()
+            .min(IVec2::new(columns - 1, rows - 1));
+        let index = (cell.y * columns + cell.x) as usize;
+        if !filled[index] {
+            filled[index] = true;
+            placed.push(*candidate);
+        }
+    }
+    placed
+}
+
+// Returns the index of the candidate closest to the target.
+fn nearest(candidates: &[IVec2], target: &IVec2) -> Option<usize> {
+    candidates
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, candidate)| (*candidate - target).length_squared())
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distinct(locations: &[IVec2]) -> bool {
+        locations
+            .iter()
+            .enumerate()
+            .all(|(i, a)| locations[i + 1..].iter().all(|b| a != b))
+    }
+
+    #[test]
+    fn test_all_random_uses_whole_grid() {
+        let mut rng = RandomStream::new(12345);
+        let size = IVec2::new(4, 3);
+        let mut seen = [false; 12];
+        for _ in 0..200 {
+            let location = Shape::All.random(&mut rng, size);
+            assert!(
+                location.cmpge(IVec2::ZERO).all() && location.cmplt(size).all(),
+                "Location {} is out of bounds",
+                location
+            );
+            seen[(location.y * size.x + location.x) as usize] = true;
+        }
+        assert!(seen.iter().all(|s| *s), "Not all locations were chosen");
+    }
+
+    #[test]
+    fn test_poisson_spacing() {
+        let mut rng = RandomStream::new(12345);
+        let shape = Shape::Square(IVec2::ZERO, IVec2::new(20, 20));
+        let placed = shape.place(
+            &mut rng,
+            IVec2::new(20, 20),
+            10,
+            &Placement::Poisson(3.0),
+            |_| true,
+        );
+
+        assert_eq!(placed.len(), 10);
+        for (i, a) in placed.iter().enumerate() {
+            for b in placed[i + 1..].iter() {
+                assert!(
+                    a.as_vec2().distance(b.as_vec2()) >= 3.0,
+                    "{} and {} are too close",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_jittered_spreads_and_respects_free() {
+        let mut rng = RandomStream::new(12345);
+        let shape = Shape::Square(IVec2::ZERO, IVec2::new(10, 10));
+        let blocked = IVec2::new(0, 0);
+        let placed = shape.place(&mut rng, IVec2::new(10, 10), 4, &Placement::Jittered, |l| {
+            *l != blocked
+        });
+
+        assert_eq!(placed.len(), 4);
+        assert!(distinct(&placed));
+        assert!(!placed.contains(&blocked));
+        // One space in each quarter of the square.
+        let mut quarters: Vec<IVec2> = placed.iter().map(|l| *l / 5).collect();
+        quarters.sort_by_key(|q| (q.x, q.y));
+        quarters.dedup();
+        assert_eq!(quarters.len(), 4);
+    }
+
+    #[test]
+    fn test_place_fills_when_crowded() {
+        let mut rng = RandomStream::new(12345);
+        let shape = Shape::Square(IVec2::ZERO, IVec2::new(3, 3));
+        let placed = shape.place(
+            &mut rng,
+            IVec2::new(3, 3),
+            20,
+            &Placement::Poisson(5.0),
+            |_| true,
+        );
+
+        // Only nine spaces exist, and all of them are used.
+        assert_eq!(placed.len(), 9);
+        assert!(distinct(&placed));
+    }
+
+    #[test]
+    fn test_formation_offsets() {
+        assert_eq!(
+            Formation::Line.offsets(3, &IVec2::Y),
+            vec![IVec2::ZERO, IVec2::new(-1, 0), IVec2::new(1, 0)]
+        );
+        assert_eq!(
+            Formation::Wedge.offsets(3, &IVec2::Y),
+            vec![IVec2::ZERO, IVec2::new(-1, -1), IVec2::new(1, -1)]
+        );
+        assert_eq!(
+            Formation::Block.offsets(4, &IVec2::X),
+            vec![
+                IVec2::ZERO,
+                IVec2::new(0, 1),
+                IVec2::new(-1, 0),
+                IVec2::new(-1, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_formation_moves_around_blocked_spaces() {
+        let mut rng = RandomStream::new(12345);
+        let size = IVec2::new(9, 9);
+        let placed = Shape::All.place(
+            &mut rng,
+            size,
+            5,
+            &Placement::Formation(Formation::Line, IVec2::Y),
+            |l| *l != IVec2::new(5, 4),
+        );
+
+        assert_eq!(placed.len(), 5);
+        assert!(distinct(&placed));
+        assert!(!placed.contains(&IVec2::new(5, 4)));
+        assert!(placed.contains(&IVec2::new(4, 4)) && placed.contains(&IVec2::new(3, 4)));
+    }
+
+    fn sorted(cells: impl Iterator<Item = IVec2>) -> Vec<IVec2> {
+        let mut cells: Vec<IVec2> = cells.collect();
+        cells.sort_by_key(|c| (c.x, c.y));
+        cells
+    }
+
+    #[test]
+    fn test_cells_match_contains() {
+        let size = IVec2::new(12, 12);
+        let shapes = [
+            Shape::All,
+            Shape::Circle(Vec2::new(2.0, 3.0), 2.5),
+            Shape::Square(IVec2::new(-2, 4), IVec2::new(5, 20)),
+            Shape::Diamond(IVec2::new(6, 6), 3),
+            Shape::Ring(Vec2::new(6.0, 6.0), 2.0, 4.0),
+            Shape::Line(IVec2::new(11, 0), IVec2::new(0, 7)),
+            Shape::Cone(IVec2::new(0, 0), IVec2::new(1, 1), 8.0, 30.0),
+            Shape::Rect(IVec2::new(10, 10), IVec2::new(3, 1)),
+            Shape::Union(vec![
+                Shape::Diamond(IVec2::new(2, 2), 2),
+                Shape::Rect(IVec2::new(9, 9), IVec2::ONE),
+            ]),
+            Shape::Intersection(vec![
+                Shape::Circle(Vec2::splat(5.0), 4.0),
+                Shape::Square(IVec2::ZERO, IVec2::splat(5)),
+            ]),
+            Shape::Difference(vec![
+                Shape::Circle(Vec2::splat(5.0), 4.0),
+                Shape::Diamond(IVec2::splat(5), 2),
+            ]),
+        ];
+        for shape in shapes {
+            let expected: Vec<IVec2> = sorted(
+                (0..size.y)
+                    .flat_map(|y| (0..size.x).map(move |x| IVec2::new(x, y)))
+                    .filter(|location| shape.contains(location)),
+            );
+            assert_eq!(sorted(shape.cells(size)), expected, "{:?}", shape);
+        }
+    }
+
+    #[test]
+    fn test_diamond_and_rect() {
+        let diamond = Shape::Diamond(IVec2::new(5, 5), 2);
+        assert!(diamond.contains(&IVec2::new(7, 5)));
+        assert!(diamond.contains(&IVec2::new(6, 6)));
+        assert!(!diamond.contains(&IVec2::new(7, 6)));
+        assert_eq!(diamond.cells(IVec2::splat(10)).count(), 13);
+
+        let rect = Shape::Rect(IVec2::new(5, 5), IVec2::new(2, 1));
+        assert_eq!(rect.cells(IVec2::splat(10)).count(), 15);
+        assert!(!rect.contains(&IVec2::new(5, 7)));
+    }
+
+    #[test]
+    fn test_ring_line_and_cone() {
+        let ring = Shape::Ring(Vec2::ZERO, 2.0, 3.0);
+        assert!(!ring.contains(&IVec2::new(1, 0)));
+        assert!(ring.contains(&IVec2::new(2, 0)));
+        assert!(ring.contains(&IVec2::new(2, 2)));
+        assert!(!ring.contains(&IVec2::new(3, 3)));
+
+        let line = Shape::Line(IVec2::new(0, 0), IVec2::new(4, 2));
+        assert_eq!(line.cells(IVec2::splat(10)).count(), 5);
+        assert!(line.contains(&IVec2::new(2, 1)));
+        assert!(!line.contains(&IVec2::new(2, 2)));
+
+        let cone = Shape::Cone(IVec2::new(5, 5), IVec2::X, 3.0, 45.0);
+        assert!(cone.contains(&IVec2::new(8, 5)));
+        assert!(cone.contains(&IVec2::new(7, 7)));
+        assert!(!cone.contains(&IVec2::new(5, 7)));
+        assert!(!cone.contains(&IVec2::new(4, 5)));
+        assert!(!cone.contains(&IVec2::new(5, 5)));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let left = Shape::Square(IVec2::ZERO, IVec2::new(4, 4));
+        let right = Shape::Square(IVec2::new(2, 2), IVec2::new(6, 6));
+        let size = IVec2::splat(10);
+
+        let union = Shape::Union(vec![left.clone(), right.clone()]);
+        assert_eq!(union.cells(size).count(), 28);
+        let intersection = Shape::Intersection(vec![left.clone(), right.clone()]);
+        assert_eq!(intersection.cells(size).count(), 4);
+        let difference = Shape::Difference(vec![left, right]);
+        assert_eq!(difference.cells(size).count(), 12);
+        assert!(!difference.contains(&IVec2::new(3, 3)));
+
+        let moved = union.translate(IVec2::new(1, -1));
+        assert!(moved.contains(&IVec2::new(1, -1)));
+        assert!(!moved.contains(&IVec2::ZERO));
+        assert!(moved.contains(&IVec2::new(6, 4)));
+    }
+
+    #[test]
+    fn test_random_stays_in_shape() {
+        let mut rng = RandomStream::new(12345);
+        let shape = Shape::Ring(Vec2::splat(5.0), 2.0, 3.0);
+        for _ in 0..100 {
+            let location = shape.random(&mut rng, IVec2::splat(10));
+            assert!(shape.contains(&location), "{} is not in the ring", location);
         }
     }
 }
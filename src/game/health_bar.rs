@@ -0,0 +1,328 @@
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use super::hud;
+use super::team;
+use super::unit;
+use crate::theme::Textures;
+
+const BACKGROUND_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+const TRAIL_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
+const SHIELD_COLOR: Color = Color::srgb(0.6, 0.8, 1.0);
+const SEGMENT_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.8);
+// Health bars are drawn in front of their unit.
+const BAR_Z: f32 = 0.5;
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(HealthBarStyle::default());
+    app.add_observer(add_health_bar);
+
+    app.add_systems(
+        Update,
+        (
+            toggle_bars.run_if(input_just_pressed(KeyCode::F3)),
+            rebuild_bars.run_if(resource_changed::<HealthBarStyle>),
+            update_bars,
+        )
+            .chain(),
+    );
+
+    app.register_type::<HealthBarStyle>();
+    app.register_type::<ShowBars>();
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+// When units show their health bars.
+pub enum ShowBars {
+    // Units that are damaged or shielded.
+    #[default]
+    Damaged,
+    Always,
+    // Only the hovered and selected units.
+    Hover,
+}
+
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct HealthBarStyle {
+    pub show: ShowBars,
+    // Width and height as a fraction of a unit's size.
+    pub size: Vec2,
+    // Health between the marks dividing the bar. No marks are drawn when zero.
+    pub segment: u32,
+    // Fill bars with the color of the unit's team instead of the health color.
+    pub team_colors: bool,
+    pub health_color: Color,
+    // Fraction of the bar the trail left by damage drains each second.
+    pub trail_speed: f32,
+}
+
+impl Default for HealthBarStyle {
+    fn default() -> Self {
+        HealthBarStyle {
+            show: ShowBars::default(),
+            size: Vec2::new(0.8, 0.1),
+            segment: 10,
+            team_colors: true,
+            health_color: Color::srgb(0.0, 1.0, 0.0),
+            trail_speed: 0.5,
+        }
+    }
+}
+
+impl HealthBarStyle {
+    // Positions of the marks along a bar, as fractions of its width.
+    pub fn segments(&self, max: u32) -> Vec<f32> {
+        if self.segment == 0 {
+            return Vec::new();
+        }
+        (1..max.div_ceil(self.segment))
+            .map(|index| (index * self.segment) as f32 / max as f32)
+            .collect()
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+// Drawn above a unit, following it as it moves.
+struct HealthBar {
+    // Health shown by the trail, which drains down to the current health after damage.
+    trail: f32,
+    width: f32,
+}
+
+impl HealthBar {
+    // Heals show straight away, damage leaves a trail that drains over time.
+    fn drain(&mut self, percent: f32, amount: f32) {
+        self.trail = (self.trail - amount).max(percent);
+    }
+}
+
+#[derive(Component)]
+struct BarFill;
+
+#[derive(Component)]
+struct BarTrail;
+
+#[derive(Component)]
+struct BarShield;
+
+// Fractions of the bar filled by health and by the shield after it. Shields beyond the missing health stretch the bar so both fit.
+fn fractions(health: &unit::Health) -> (f32, f32) {
+    let scale = health.max.max(health.total()) as f32;
+    (health.current as f32 / scale, health.shield as f32 / scale)
+}
+
+fn spawn_bar(
+    commands: &mut Commands,
+    unit: Entity,
+    health: &unit::Health,
+    style: &HealthBarStyle,
+    textures: &Textures,
+) {
+    let unit_size = textures.unit.scale();
+    let size = style.size * unit_size;
+    let left = Vec3::new(size.x * -0.5, 0.0, 0.0);
+    // Sits just above the top of the unit's sprite.
+    let height = unit_size.y * (0.5 - textures.unit.anchor().as_vec().y) + size.y;
+    let bar = commands
+        .spawn((
+            Name::new("HealthBar"),
+            HealthBar {
+                trail: fractions(health).0,
+                width: size.x,
+            },
+            Transform::from_xyz(0.0, height, BAR_Z),
+            Visibility::Hidden,
+            ChildOf(unit),
+        ))
+        .id();
+    commands.spawn((
+        Sprite::from_color(BACKGROUND_COLOR, size),
+        Transform::default(),
+        ChildOf(bar),
+    ));
+    for (marker, color, z) in [(true, TRAIL_COLOR, 0.01), (false, style.health_color, 0.02)] {
+        let mut entity = commands.spawn((
+            Sprite::from_color(color, size),
+            Anchor::CENTER_LEFT,
+            Transform::from_translation(left + Vec3::Z * z),
+            ChildOf(bar),
+        ));
+        if marker {
+            entity.insert(BarTrail);
+        } else {
+            entity.insert(BarFill);
+        }
+    }
+    commands.spawn((
+        BarShield,
+        Sprite::from_color(SHIELD_COLOR, size),
+        Anchor::CENTER_LEFT,
+        Transform::from_translation(left + Vec3::Z * 0.025),
+        ChildOf(bar),
+    ));
+    for fraction in style.segments(health.max) {
+        commands.spawn((
+            Sprite::from_color(SEGMENT_COLOR, Vec2::new(1.0, size.y)),
+            Transform::from_translation(left + Vec3::new(size.x * fraction, 0.0, 0.03)),
+            ChildOf(bar),
+        ));
+    }
+}
+
+fn add_health_bar(
+    trigger: On<Add, unit::Unit>,
+    mut commands: Commands,
+    style: Res<HealthBarStyle>,
+    textures: Option<Res<Textures>>,
+    health_query: Query<&unit::Health>,
+) {
+    let (Ok(health), Some(textures)) = (health_query.get(trigger.event_target()), textures) else {
+        return;
+    };
+    spawn_bar(
+        &mut commands,
+        trigger.event_target(),
+        health,
+        &style,
+        &textures,
+    );
+}
+
+fn toggle_bars(mut style: ResMut<HealthBarStyle>) {
+    style.show = match style.show {
+        ShowBars::Damaged => ShowBars::Always,
+        ShowBars::Always => ShowBars::Hover,
+        ShowBars::Hover => ShowBars::Damaged,
+    };
+}
+
+// Rebuilds every bar so changes to the style apply to units already on the field.
+fn rebuild_bars(
+    mut commands: Commands,
+    style: Res<HealthBarStyle>,
+    textures: Option<Res<Textures>>,
+    bar_query: Query<Entity, With<HealthBar>>,
+    unit_query: Query<(Entity, &unit::Health), With<unit::Unit>>,
+) {
+    let Some(textures) = textures else {
+        return;
+    };
+    for entity in bar_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    for (entity, health) in unit_query.iter() {
+        spawn_bar(&mut commands, entity, health, &style, &textures);
+    }
+}
+
+fn update_bars(
+    time: Res<Time>,
+    style: Res<HealthBarStyle>,
+    teams: Res<team::Teams>,
+    selection: Res<hud::Selection>,
+    unit_query: Query<(&unit::Health, &unit::Unit)>,
+    mut bar_query: Query<(&mut HealthBar, &mut Visibility, &ChildOf, &Children)>,
+    mut fill_query: Query<
+        (&mut Sprite, &mut Transform, Has<BarTrail>, Has<BarShield>),
+        Or<(With<BarFill>, With<BarTrail>, With<BarShield>)>,
+    >,
+) {
+    let drain = style.trail_speed * time.delta_secs();
+    for (mut bar, mut visibility, parent, children) in bar_query.iter_mut() {
+        let Ok((health, unit)) = unit_query.get(parent.parent()) else {
+            continue;
+        };
+        let (percent, shield) = fractions(health);
+        bar.drain(percent, drain);
+        let shown = match style.show {
+            ShowBars::Always => true,
+            ShowBars::Damaged => health.current < health.max || health.shield > 0,
+            ShowBars::Hover => {
+                selection.hovered == Some(parent.parent())
+                    || selection.selected == Some(parent.parent())
+            }
+        };
+        visibility.set_if_neq(if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+        let fill_color = if style.team_colors {
+            teams.color(unit.team)
+        } else {
+            style.health_color
+        };
+        for child in children.iter() {
+            let Ok((mut sprite, mut transform, is_trail, is_shield)) = fill_query.get_mut(child)
+            else {
+                continue;
+            };
+            let fraction = if is_shield {
+                // The shield starts where the health ends.
+                transform.translation.x = bar.width * (percent - 0.5);
+                shield
+            } else if is_trail {
+                bar.trail
+            } else {
+                percent
+            };
+            let height = sprite.custom_size.map_or(0.0, |size| size.y);
+            sprite.custom_size = Some(Vec2::new(bar.width * fraction, height));
+            if !is_trail && !is_shield {
+                sprite.color = fill_color;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segments() {
+        let style = HealthBarStyle::default();
+        assert_eq!(style.segments(50), vec![0.2, 0.4, 0.6, 0.8]);
+        assert_eq!(style.segments(25), vec![0.4, 0.8]);
+        assert!(style.segments(10).is_empty());
+        assert!(
+            HealthBarStyle {
+                segment: 0,
+                ..default()
+            }
+            .segments(50)
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_trail_drains() {
+        let mut bar = HealthBar {
+            trail: 1.0,
+            width: 10.0,
+        };
+        bar.drain(0.5, 0.2);
+        assert_eq!(bar.trail, 0.8);
+        bar.drain(0.5, 0.5);
+        assert_eq!(bar.trail, 0.5);
+        // Healing past the trail moves it up at once.
+        bar.drain(0.9, 0.1);
+        assert_eq!(bar.trail, 0.9);
+    }
+
+    #[test]
+    fn test_shield_fractions() {
+        let health = unit::Health {
+            current: 5,
+            max: 10,
+            shield: 2,
+        };
+        assert_eq!(fractions(&health), (0.5, 0.2));
+        // A shield larger than the missing health stretches the bar.
+        let health = unit::Health::new(6).with_shield(2);
+        assert_eq!(fractions(&health), (0.75, 0.25));
+        assert_eq!(fractions(&unit::Health::new(4)), (1.0, 0.0));
+    }
+}
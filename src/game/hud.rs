@@ -0,0 +1,481 @@
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::deploy;
+use super::game;
+use super::grid;
+use super::object;
+use super::pick;
+use super::team;
+use super::unit;
+
+// Most groups shown in the turn order strip, starting with the group that acts next.
+const STRIP_LENGTH: usize = 6;
+const PANEL_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+const HIGHLIGHT_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.25);
+const FONT_SIZE: f32 = 16.0;
+// Distance from the cursor to the corner of the tooltip, in logical pixels.
+const TOOLTIP_OFFSET: Vec2 = Vec2::new(16.0, 16.0);
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(Selection::default());
+
+    app.add_systems(Startup, spawn_hud);
+    app.add_systems(
+        Update,
+        (
+            (
+                hover_unit,
+                // Clicks place units while deploying, and only select them once the battle begins.
+                select_unit.run_if(
+                    input_just_pressed(MouseButton::Left)
+                        .and(not(any_with_component::<deploy::Deployment>)),
+                ),
+            )
+                .chain(),
+            (
+                update_round,
+                update_team_counts,
+                update_strip,
+                update_panel,
+                update_tooltip,
+            ),
+        )
+            .chain(),
+    );
+
+    app.register_type::<Selection>();
+}
+
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+#[reflect(Resource)]
+// The units shown in the unit panel. The hovered unit is shown while nothing is selected.
+pub struct Selection {
+    pub selected: Option<Entity>,
+    pub hovered: Option<Entity>,
+}
+
+impl Selection {
+    pub fn shown(&self) -> Option<Entity> {
+        self.selected.or(self.hovered)
+    }
+}
+
+#[derive(Component)]
+struct RoundText;
+
+#[derive(Component)]
+struct TeamCounts;
+
+#[derive(Component, Default)]
+// Row of upcoming turns, remembering what it shows so it is only rebuilt when the order changes.
+struct TurnStrip {
+    shown: Vec<StripGroup>,
+}
+
+#[derive(Component)]
+struct UnitPanel;
+
+#[derive(Component)]
+// Follows the cursor, describing what is in the hovered space.
+struct Tooltip;
+
+#[derive(Clone, Debug, PartialEq)]
+// A group of units that act together in the turn order, counted by team.
+struct StripGroup {
+    current: bool,
+    teams: Vec<(u32, usize)>,
+}
+
+fn text(value: impl Into<String>, color: Color) -> impl Bundle {
+    (
+        Text::new(value),
+        TextFont::from_font_size(FONT_SIZE),
+        TextColor(color),
+    )
+}
+
+fn panel() -> impl Bundle {
+    (
+        Node {
+            padding: UiRect::all(Val::Px(6.0)),
+            column_gap: Val::Px(12.0),
+            ..default()
+        },
+        BackgroundColor(PANEL_COLOR),
+    )
+}
+
+fn spawn_hud(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Hud"),
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::SpaceBetween,
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        children![
+            (
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                children![
+                    (
+                        panel(),
+                        children![
+                            (RoundText, text("", Color::WHITE)),
+                            (TeamCounts, text("", Color::WHITE))
+                        ],
+                    ),
+                    (TurnStrip::default(), panel()),
+                ],
+            ),
+            (
+                UnitPanel,
+                text("", Color::WHITE),
+                Node {
+                    align_self: AlignSelf::FlexStart,
+                    padding: UiRect::all(Val::Px(6.0)),
+                    display: Display::None,
+                    ..default()
+                },
+                BackgroundColor(PANEL_COLOR),
+            ),
+        ],
+    ));
+    commands.spawn((
+        Name::new("Tooltip"),
+        Tooltip,
+        text("", Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            padding: UiRect::all(Val::Px(4.0)),
+            display: Display::None,
+            ..default()
+        },
+        BackgroundColor(PANEL_COLOR),
+        GlobalZIndex(1),
+    ));
+}
+
+// Tracks the unit under the cursor, forgetting selected units once they are gone.
+fn hover_unit(
+    mut selection: ResMut<Selection>,
+    hovered: Res<pick::Hovered>,
+    grid_query: Query<&grid::Grid>,
+    unit_query: Query<(), With<unit::Unit>>,
+) {
+    if selection
+        .selected
+        .is_some_and(|entity| unit_query.get(entity).is_err())
+    {
+        selection.selected = None;
+    }
+    let unit = hovered.entity(&grid::EntityKind::Unit, &grid_query);
+    if selection.hovered != unit {
+        selection.hovered = unit;
+    }
+}
+
+// Clicking a unit selects it, clicking anything else clears the selection.
+pub fn select_unit(mut selection: ResMut<Selection>) {
+    selection.selected = selection.hovered;
+}
+
+fn update_round(
+    turn_query: Query<(&game::TurnOrder, Has<deploy::Deployment>)>,
+    mut text_query: Query<&mut Text, With<RoundText>>,
+) {
+    let value = match turn_query.single() {
+        Ok((_, true)) => "Deploying".to_string(),
+        Ok((turns, false)) if turns.round == 0 => "Ready".to_string(),
+        Ok((turns, false)) => format!("Round {}", turns.round),
+        Err(_) => String::new(),
+    };
+    for mut text in text_query.iter_mut() {
+        if text.0 != value {
+            text.0 = value.clone();
+        }
+    }
+}
+
+fn update_team_counts(
+    mut commands: Commands,
+    teams: Res<team::Teams>,
+    unit_query: Query<&unit::Unit>,
+    counts_query: Query<(Entity, Option<&Children>), With<TeamCounts>>,
+    mut span_query: Query<(&mut TextSpan, &mut TextColor)>,
+) {
+    let counts = team_counts(unit_query.iter().map(|unit| unit.team));
+    for (entity, children) in counts_query.iter() {
+        let spans: Vec<Entity> = children.map_or(Vec::new(), |c| c.iter().collect());
+        if spans.len() != counts.len() {
+            commands.entity(entity).despawn_children();
+            for _ in &counts {
+                commands
+                    .entity(entity)
+                    .with_child((TextSpan::default(), TextColor::default()));
+            }
+            continue;
+        }
+        for (span, (team, count)) in spans.into_iter().zip(&counts) {
+            let Ok((mut text, mut color)) = span_query.get_mut(span) else {
+                continue;
+            };
+            let name = teams
+                .get(*team)
+                .map_or(format!("Team {}", team), |t| t.name.clone());
+            let value = format!("  {}: {}", name, count);
+            if text.0 != value {
+                text.0 = value;
+            }
+            let team_color = teams.color(*team);
+            if color.0 != team_color {
+                color.0 = team_color;
+            }
+        }
+    }
+}
+
+// Rebuilds the turn order strip whenever the upcoming groups change.
+fn update_strip(
+    mut commands: Commands,
+    teams: Res<team::Teams>,
+    turn_query: Query<&game::TurnOrder>,
+    unit_query: Query<&unit::Unit>,
+    mut strip_query: Query<(Entity, &mut TurnStrip)>,
+) {
+    let groups = turn_query.single().map_or(Vec::new(), |turns| {
+        strip(turns, |entity| unit_query.get(entity).ok().map(|u| u.team))
+    });
+    for (entity, mut strip) in strip_query.iter_mut() {
+        if strip.shown == groups {
+            continue;
+        }
+        commands.entity(entity).despawn_children();
+        for group in &groups {
+            let chips: Vec<_> = group
+                .teams
+                .iter()
+                .map(|(team, count)| text(format!("{}", count), teams.color(*team)))
+                .collect();
+            commands
+                .entity(entity)
+                .with_child((
+                    Node {
+                        padding: UiRect::horizontal(Val::Px(4.0)),
+                        column_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                    BackgroundColor(if group.current {
+                        HIGHLIGHT_COLOR
+                    } else {
+                        Color::NONE
+                    }),
+                ))
+                .with_children(|parent| {
+                    for chip in chips {
+                        parent.spawn(chip);
+                    }
+                });
+        }
+        strip.shown = groups.clone();
+    }
+}
+
+fn update_panel(
+    selection: Res<Selection>,
+    teams: Res<team::Teams>,
+    unit_query: Query<(
+        &unit::Unit,
+        Option<&unit::Health>,
+        Option<&unit::Movement>,
+        Option<&unit::Attacks>,
+    )>,
+    mut panel_query: Query<(&mut Text, &mut TextColor, &mut Node), With<UnitPanel>>,
+) {
+    let shown = selection
+        .shown()
+        .and_then(|entity| unit_query.get(entity).ok());
+    for (mut text, mut color, mut node) in panel_query.iter_mut() {
+        let Some((unit, health, movement, attacks)) = shown else {
+            if node.display != Display::None {
+                node.display = Display::None;
+            }
+            continue;
+        };
+        let mut lines = vec![
+            teams
+                .get(unit.team)
+                .map_or(format!("Team {}", unit.team), |team| team.name.clone()),
+        ];
+        if let Some(health) = health {
+            lines.push(format!("Health {}/{}", health.current, health.max));
+            if health.shield > 0 {
+                lines.push(format!("Shield {}", health.shield));
+            }
+        }
+        if let Some(movement) = movement {
+            lines.push(format!("Move {}", movement.spaces));
+        }
+        if let Some(attacks) = attacks {
+            lines.push(format!(
+                "Attack {} damage, range {}",
+                attacks.damage, attacks.range
+            ));
+        }
+        let value = lines.join("\n");
+        if text.0 != value {
+            text.0 = value;
+        }
+        let team_color = teams.color(unit.team);
+        if color.0 != team_color {
+            color.0 = team_color;
+        }
+        if node.display != Display::Flex {
+            node.display = Display::Flex;
+        }
+    }
+}
+
+// Describes the unit, object and tile in the hovered space next to the cursor.
+fn update_tooltip(
+    hovered: Res<pick::Hovered>,
+    teams: Res<team::Teams>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    grid_query: Query<&grid::Grid>,
+    unit_query: Query<(&unit::Unit, Option<&unit::Health>)>,
+    object_query: Query<&object::MapObject>,
+    mut tooltip_query: Query<(&mut Text, &mut Node), With<Tooltip>>,
+) {
+    let cursor = window_query
+        .single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+    let space = hovered.space.and_then(|(grid_entity, location)| {
+        let grid = grid_query.get(grid_entity).ok()?;
+        Some((location, grid.space(&location)?))
+    });
+    let mut lines = Vec::new();
+    if let (Some(_), Some((location, space))) = (cursor, space) {
+        if let Some((unit, health)) = space.unit.and_then(|e| unit_query.get(e).ok()) {
+            let name = teams
+                .get(unit.team)
+                .map_or(format!("Team {}", unit.team), |team| team.name.clone());
+            lines.push(match health {
+                Some(health) => format!("{} unit, {}/{}", name, health.current, health.max),
+                None => format!("{} unit", name),
+            });
+        }
+        if let Some(object) = space.object.and_then(|e| object_query.get(e).ok()) {
+            lines.push(object.describe());
+        }
+        lines.push(format!(
+            "({}, {}) height {}",
+            location.x, location.y, space.height
+        ));
+        if space.cost > 0 {
+            lines.push(format!("Costs {} more to enter", space.cost));
+        }
+    }
+    for (mut text, mut node) in tooltip_query.iter_mut() {
+        let (Some(cursor), false) = (cursor, lines.is_empty()) else {
+            if node.display != Display::None {
+                node.display = Display::None;
+            }
+            continue;
+        };
+        let value = lines.join("\n");
+        if text.0 != value {
+            text.0 = value;
+        }
+        node.display = Display::Flex;
+        node.left = Val::Px(cursor.x + TOOLTIP_OFFSET.x);
+        node.top = Val::Px(cursor.y + TOOLTIP_OFFSET.y);
+    }
+}
+
+// Counts the units of each team, in team order.
+fn team_counts(teams: impl Iterator<Item = u32>) -> Vec<(u32, usize)> {
+    let mut counts: Vec<(u32, usize)> = Vec::new();
+    for team in teams {
+        match counts.iter_mut().find(|(t, _)| *t == team) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((team, 1)),
+        }
+    }
+    counts.sort();
+    counts
+}
+
+// The groups that act next, skipping any left without living units.
+fn strip(turns: &game::TurnOrder, team_of: impl Fn(Entity) -> Option<u32>) -> Vec<StripGroup> {
+    let length = turns.order.len();
+    (0..length)
+        .map(|step| (turns.index + step) % length)
+        .filter_map(|index| {
+            let teams = team_counts(turns.order[index].iter().filter_map(|e| team_of(*e)));
+            (!teams.is_empty()).then_some(StripGroup {
+                current: false,
+                teams,
+            })
+        })
+        .take(STRIP_LENGTH)
+        .enumerate()
+        .map(|(position, group)| StripGroup {
+            current: position == 0,
+            ..group
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_team_counts() {
+        assert_eq!(
+            team_counts([2, 1, 2, 3, 2].into_iter()),
+            vec![(1, 1), (2, 3), (3, 1)]
+        );
+        assert!(team_counts(std::iter::empty()).is_empty());
+    }
+
+    #[test]
+    fn test_strip() {
+        let entities: Vec<Entity> = (0..4).filter_map(Entity::from_raw_u32).collect();
+        let mut turns = game::TurnOrder::default();
+        turns.add_entity(entities[0], 0);
+        turns.add_entity(entities[1], 1);
+        turns.add_entity(entities[2], 1);
+        turns.add_entity(entities[3], 2);
+        turns.index = 1;
+        // The last entity is gone, so its group is skipped.
+        let team_of = |entity: Entity| {
+            entities[..3]
+                .iter()
+                .position(|e| *e == entity)
+                .map(|index| index as u32 % 2 + 1)
+        };
+        assert_eq!(
+            strip(&turns, team_of),
+            vec![
+                StripGroup {
+                    current: true,
+                    teams: vec![(1, 1), (2, 1)],
+                },
+                StripGroup {
+                    current: false,
+                    teams: vec![(1, 1)],
+                },
+            ]
+        );
+    }
+}
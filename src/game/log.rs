@@ -0,0 +1,441 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::ecs::query::QueryFilter;
+use bevy::input::mouse::MouseScrollUnit;
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+use serde::Serialize;
+
+use super::deploy;
+use super::effect;
+use super::game;
+use super::object;
+use super::team;
+use super::unit;
+
+// The log is written here when the battle ends, as text and as RON.
+const LOG_PATH: &str = "saves/battle_log";
+const PANEL_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+const PANEL_SIZE: Vec2 = Vec2::new(360.0, 160.0);
+const FONT_SIZE: f32 = 13.0;
+// Pixels scrolled for each line of the mouse wheel.
+const SCROLL_LINE: f32 = 16.0;
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(BattleLog::default());
+    app.add_observer(log_move);
+    app.add_observer(log_attack);
+    app.add_observer(log_health);
+    app.add_observer(log_death);
+
+    app.add_systems(Startup, spawn_panel);
+    app.add_systems(Update, (end_battle, update_panel, scroll_panel).chain());
+
+    app.register_type::<BattleLog>();
+}
+
+#[derive(Clone, Debug, PartialEq, Reflect, Serialize)]
+// Whoever took part in an entry, named as they were at the time.
+pub struct Actor {
+    pub name: String,
+    pub team: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Reflect, Serialize)]
+pub enum Action {
+    Move { from: (i32, i32), to: (i32, i32) },
+    Attack,
+    Damage,
+    Flanked,
+    Heal,
+    Miss,
+    Death,
+    Victory,
+    Draw,
+}
+
+#[derive(Clone, Debug, PartialEq, Reflect, Serialize)]
+pub struct LogEntry {
+    pub round: u32,
+    pub actor: Option<Actor>,
+    pub action: Action,
+    pub target: Option<Actor>,
+    // Spaces moved, or health lost or gained.
+    pub amount: Option<u32>,
+}
+
+impl LogEntry {
+    pub fn line(&self) -> String {
+        let actor = self.actor.as_ref().map_or("Someone", |a| a.name.as_str());
+        let target = self
+            .target
+            .as_ref()
+            .map_or("something", |t| t.name.as_str());
+        let amount = self.amount.unwrap_or(0);
+        let text = match &self.action {
+            Action::Move { from, to } => format!(
+                "{} moves from ({}, {}) to ({}, {})",
+                actor, from.0, from.1, to.0, to.1
+            ),
+            Action::Attack => format!("{} attacks {}", actor, target),
+            Action::Damage => format!("{} takes {} damage", target, amount),
+            Action::Flanked => format!("{} takes {} flanking damage", target, amount),
+            Action::Heal => format!("{} heals {}", target, amount),
+            Action::Miss => format!("{} takes no damage", target),
+            Action::Death => format!("{} is defeated", actor),
+            Action::Victory => format!("{} wins the battle", actor),
+            Action::Draw => "The battle ends with no one standing".to_string(),
+        };
+        format!("[{}] {}", self.round, text)
+    }
+}
+
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+#[reflect(Resource)]
+// Everything that happened during the battle, in order.
+pub struct BattleLog {
+    pub entries: Vec<LogEntry>,
+    // Set once the battle is over and the log has been written out.
+    pub ended: bool,
+}
+
+impl BattleLog {
+    pub fn text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| entry.line())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // Writes the log as text for reading and as RON for tools.
+    pub fn export(&self, path: &str) -> Result {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(format!("{}.txt", path), self.text())?;
+        fs::write(
+            format!("{}.ron", path),
+            ron::ser::to_string_pretty(&self.entries, ron::ser::PrettyConfig::default())?,
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(Component)]
+struct LogPanel;
+
+#[derive(Component)]
+struct LogText;
+
+// Names a unit by its team, or an object by its kind.
+fn actor(
+    entity: Entity,
+    teams: &team::Teams,
+    unit_query: &Query<&unit::Unit>,
+    object_query: &Query<&object::MapObject>,
+) -> Actor {
+    if let Ok(unit) = unit_query.get(entity) {
+        let team = teams
+            .get(unit.team)
+            .map_or(format!("Team {}", unit.team), |team| team.name.clone());
+        return Actor {
+            name: format!("{} unit {}", team, entity.index_u32()),
+            team: Some(unit.team),
+        };
+    }
+    let kind = object_query.get(entity).map_or("Object", |o| o.name());
+    Actor {
+        name: format!("{} {}", kind, entity.index_u32()),
+        team: None,
+    }
+}
+
+fn round<F: QueryFilter>(turn_query: &Query<&game::TurnOrder, F>) -> u32 {
+    turn_query
+        .iter()
+        .map(|turns| turns.round)
+        .max()
+        .unwrap_or(0)
+}
+
+fn log_move(
+    trigger: On<game::Moved>,
+    mut log: ResMut<BattleLog>,
+    teams: Res<team::Teams>,
+    unit_query: Query<&unit::Unit>,
+    object_query: Query<&object::MapObject>,
+    turn_query: Query<&game::TurnOrder>,
+) {
+    let path = trigger.event().path();
+    let (Some(from), Some(to)) = (path.first(), path.last()) else {
+        return;
+    };
+    log.entries.push(LogEntry {
+        round: round(&turn_query),
+        actor: Some(actor(
+            trigger.event_target(),
+            &teams,
+            &unit_query,
+            &object_query,
+        )),
+        action: Action::Move {
+            from: (from.x, from.y),
+            to: (to.x, to.y),
+        },
+        target: None,
+        amount: Some(path.len() as u32 - 1),
+    });
+}
+
+fn log_attack(
+    trigger: On<game::Attack>,
+    mut log: ResMut<BattleLog>,
+    teams: Res<team::Teams>,
+    unit_query: Query<&unit::Unit>,
+    object_query: Query<&object::MapObject>,
+    turn_query: Query<&game::TurnOrder>,
+) {
+    log.entries.push(LogEntry {
+        round: round(&turn_query),
+        actor: Some(actor(
+            trigger.event_target(),
+            &teams,
+            &unit_query,
+            &object_query,
+        )),
+        action: Action::Attack,
+        target: Some(actor(
+            trigger.event().target(),
+            &teams,
+            &unit_query,
+            &object_query,
+        )),
+        amount: None,
+    });
+}
+
+// Changes in health are logged from the combat text floated above whoever was hit.
+fn log_health(
+    trigger: On<effect::Effect>,
+    mut log: ResMut<BattleLog>,
+    teams: Res<team::Teams>,
+    unit_query: Query<&unit::Unit>,
+    object_query: Query<&object::MapObject>,
+    turn_query: Query<&game::TurnOrder>,
+) {
+    let effect::Effect::Text(entity, text) = trigger.event() else {
+        return;
+    };
+    let (action, amount) = match text {
+        effect::CombatText::Damage(amount) => (Action::Damage, Some(*amount)),
+        effect::CombatText::Flanked(amount) => (Action::Flanked, Some(*amount)),
+        effect::CombatText::Heal(amount) => (Action::Heal, Some(*amount)),
+        effect::CombatText::Miss => (Action::Miss, None),
+    };
+    log.entries.push(LogEntry {
+        round: round(&turn_query),
+        actor: None,
+        action,
+        target: Some(actor(*entity, &teams, &unit_query, &object_query)),
+        amount,
+    });
+}
+
+fn log_death(
+    trigger: On<unit::Death>,
+    mut log: ResMut<BattleLog>,
+    teams: Res<team::Teams>,
+    unit_query: Query<&unit::Unit>,
+    object_query: Query<&object::MapObject>,
+    turn_query: Query<&game::TurnOrder>,
+) {
+    log.entries.push(LogEntry {
+        round: round(&turn_query),
+        actor: Some(actor(
+            trigger.event_target(),
+            &teams,
+            &unit_query,
+            &object_query,
+        )),
+        action: Action::Death,
+        target: None,
+        amount: None,
+    });
+}
+
+// Returns the teams left standing once none of them are hostile to each other.
+fn survivors(teams: &team::Teams, remaining: impl Iterator<Item = u32>) -> Option<Vec<u32>> {
+    let mut standing: Vec<u32> = remaining.collect();
+    standing.sort();
+    standing.dedup();
+    let fighting = standing
+        .iter()
+        .any(|a| standing.iter().any(|b| teams.is_hostile(*a, *b)));
+    (!fighting).then_some(standing)
+}
+
+// Ends the battle once no hostile units remain, recording the outcome and writing out the log.
+fn end_battle(
+    mut log: ResMut<BattleLog>,
+    teams: Res<team::Teams>,
+    turn_query: Query<&game::TurnOrder, Without<deploy::Deployment>>,
+    unit_query: Query<&unit::Unit>,
+) {
+    let round = round(&turn_query);
+    if log.ended || round == 0 {
+        return;
+    }
+    let Some(standing) = survivors(&teams, unit_query.iter().map(|unit| unit.team)) else {
+        return;
+    };
+    if standing.is_empty() {
+        log.entries.push(LogEntry {
+            round,
+            actor: None,
+            action: Action::Draw,
+            target: None,
+            amount: None,
+        });
+    }
+    for team in standing {
+        log.entries.push(LogEntry {
+            round,
+            actor: Some(Actor {
+                name: teams
+                    .get(team)
+                    .map_or(format!("Team {}", team), |t| t.name.clone()),
+                team: Some(team),
+            }),
+            action: Action::Victory,
+            target: None,
+            amount: None,
+        });
+    }
+    log.ended = true;
+    match log.export(LOG_PATH) {
+        Ok(()) => info!("Battle log written to {}", LOG_PATH),
+        Err(error) => warn!("Failed to write the battle log: {}", error),
+    }
+}
+
+fn spawn_panel(mut commands: Commands) {
+    commands.spawn((
+        Name::new("BattleLog"),
+        LogPanel,
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(8.0),
+            bottom: Val::Px(8.0),
+            width: Val::Px(PANEL_SIZE.x),
+            height: Val::Px(PANEL_SIZE.y),
+            padding: UiRect::all(Val::Px(6.0)),
+            flex_direction: FlexDirection::Column,
+            overflow: Overflow::scroll_y(),
+            ..default()
+        },
+        ScrollPosition::default(),
+        RelativeCursorPosition::default(),
+        BackgroundColor(PANEL_COLOR),
+        children![(
+            LogText,
+            Text::new(""),
+            TextFont::from_font_size(FONT_SIZE),
+            TextColor(Color::WHITE),
+        )],
+    ));
+}
+
+// Shows the log, keeping the newest entries in view as they are added.
+fn update_panel(
+    log: Res<BattleLog>,
+    mut text_query: Query<&mut Text, With<LogText>>,
+    mut panel_query: Query<&mut ScrollPosition, With<LogPanel>>,
+) {
+    if !log.is_changed() {
+        return;
+    }
+    for mut text in text_query.iter_mut() {
+        text.0 = log.text();
+    }
+    for mut scroll in panel_query.iter_mut() {
+        scroll.y = f32::MAX;
+    }
+}
+
+// Scrolls the log with the mouse wheel while the cursor is over it.
+fn scroll_panel(
+    mut input: MessageReader<MouseWheel>,
+    mut panel_query: Query<
+        (&mut ScrollPosition, &ComputedNode, &RelativeCursorPosition),
+        With<LogPanel>,
+    >,
+) {
+    for event in input.read() {
+        let amount = match event.unit {
+            MouseScrollUnit::Line => event.y * SCROLL_LINE,
+            MouseScrollUnit::Pixel => event.y,
+        };
+        for (mut scroll, node, cursor) in panel_query.iter_mut() {
+            if cursor.cursor_over() {
+                // Scroll from where the panel is shown, as the layout keeps it within the text.
+                let shown = node.scroll_position.y * node.inverse_scale_factor();
+                scroll.y = (shown - amount).max(0.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> Option<Actor> {
+        Some(Actor {
+            name: name.to_string(),
+            team: Some(1),
+        })
+    }
+
+    #[test]
+    fn test_lines() {
+        let attack = LogEntry {
+            round: 2,
+            actor: named("Red unit 4"),
+            action: Action::Attack,
+            target: named("Blue unit 9"),
+            amount: None,
+        };
+        assert_eq!(attack.line(), "[2] Red unit 4 attacks Blue unit 9");
+        let moved = LogEntry {
+            action: Action::Move {
+                from: (1, 2),
+                to: (3, 2),
+            },
+            target: None,
+            amount: Some(2),
+            ..attack.clone()
+        };
+        assert_eq!(moved.line(), "[2] Red unit 4 moves from (1, 2) to (3, 2)");
+        let damage = LogEntry {
+            actor: None,
+            action: Action::Flanked,
+            amount: Some(6),
+            ..attack
+        };
+        assert_eq!(damage.line(), "[2] Blue unit 9 takes 6 flanking damage");
+    }
+
+    #[test]
+    fn test_survivors() {
+        let teams = team::Teams::default().with_team(3, "Green", 2, team::Controller::Computer);
+        assert_eq!(survivors(&teams, [1, 2, 1].into_iter()), None);
+        assert_eq!(survivors(&teams, [2, 2].into_iter()), Some(vec![2]));
+        assert_eq!(survivors(&teams, std::iter::empty()), Some(vec![]));
+
+        let allied = teams.with_stance(1, 3, team::Stance::Allied);
+        assert_eq!(survivors(&allied, [3, 1].into_iter()), Some(vec![1, 3]));
+    }
+}
@@ -3,32 +3,60 @@ use bevy::prelude::*;
 mod animate;
 mod background;
 mod camera;
+mod deploy;
 mod effect;
+mod forced;
 mod game;
 mod gizmo;
 mod grid;
+mod health_bar;
+mod hud;
+mod log;
+mod object;
+mod overlay;
+mod pick;
+mod save;
+mod team;
 mod tiles;
 mod unit;
+mod zone;
 
 use crate::random::RandomSource;
+use crate::theme::AssetState;
 use crate::theme::Textures;
-use crate::util::cords;
 
 pub fn plugin(app: &mut bevy::prelude::App) {
     app.add_plugins(animate::plugin);
     app.add_plugins(background::plugin);
     app.add_plugins(camera::plugin);
+    app.add_plugins(deploy::plugin);
     app.add_plugins(effect::plugin);
+    app.add_plugins(forced::plugin);
     app.add_plugins(game::plugin);
     app.add_plugins(gizmo::plugin);
     app.add_plugins(grid::plugin);
+    app.add_plugins(health_bar::plugin);
+    app.add_plugins(hud::plugin);
+    app.add_plugins(log::plugin);
+    app.add_plugins(object::plugin);
+    app.add_plugins(overlay::plugin);
+    app.add_plugins(pick::plugin);
+    app.add_plugins(save::plugin);
+    app.add_plugins(team::plugin);
     app.add_plugins(tiles::plugin);
     app.add_plugins(unit::plugin);
+    app.add_plugins(zone::plugin);
 
-    app.add_systems(Startup, init);
+    // The battle is set up once its textures are ready to draw.
+    app.add_systems(OnEnter(AssetState::Ready), init);
 }
 
-fn init(mut commands: Commands, sprites: Res<Textures>, mut rand: ResMut<RandomSource>) {
+fn init(
+    mut commands: Commands,
+    sprites: Res<Textures>,
+    teams: Res<team::Teams>,
+    mut rand: ResMut<RandomSource>,
+) {
     let root = commands.spawn_empty().id();
     let size = IVec2::new(40, 40);
     let mut grid = grid::Grid::new(size);
@@ -39,78 +67,206 @@ fn init(mut commands: Commands, sprites: Res<Textures>, mut rand: ResMut<RandomS
         size.as_vec2() * scale.scale().as_vec2() * 0.5,
     ));
 
+    // Raise a few hills across the map.
+    let all_spaces = grid::selection::Shape::Square(IVec2::ZERO, size);
+    for _ in 0..8 {
+        let center = all_spaces.random(rand.map(), size);
+        let peak: i32 = rand.map().range(1..4);
+        for x in (center.x - peak * 2)..=(center.x + peak * 2) {
+            for y in (center.y - peak * 2)..=(center.y + peak * 2) {
+                let location = IVec2::new(x, y);
+                let distance = (location - center).abs().max_element();
+                let height = peak - distance / 2;
+                if height > grid.height(&location) {
+                    grid.set_height(&location, height);
+                }
+            }
+        }
+    }
+
     let step_range = 2..4;
 
     let spawn_space = IVec2::new(size.x, size.y / 3);
+    let team_1 = unit::Unit { team: 1 };
     let team_1_spaces = grid::selection::Shape::Square(IVec2::ZERO, spawn_space);
-    for _ in 0..4 {
-        if let Some(location) = grid.nearest_empty(
-            &grid::EntityKind::Unit,
-            &team_1_spaces.random(rand.as_mut()),
-            &IVec2::ZERO,
-            team_1_spaces.clone(),
-        ) {
-            turns.add_entity_optional(
-                grid.spawn(
-                    &mut commands,
-                    &grid::EntityKind::Unit,
-                    &location,
-                    root,
-                    (
-                        Sprite {
-                            color: Color::linear_rgb(1.0, 0.0, 0.0),
-                            ..sprites.unit.sprite()
-                        },
-                        Transform::from_translation(cords::location_to_translation(
-                            &location,
-                            scale.scale(),
-                            1,
-                        )),
-                        unit::Unit { team: 1 },
-                        unit::Movement::new(rand::random_range(step_range.clone())),
-                        unit::Health::new(50),
-                        unit::Attacks::new(3, 10),
-                    ),
+    let team_1_locations = team_1_spaces.place(
+        rand.map(),
+        size,
+        4,
+        &grid::selection::Placement::Formation(grid::selection::Formation::Wedge, IVec2::Y),
+        |location| grid.can_enter(&grid::EntityKind::Unit, location),
+    );
+    for location in team_1_locations {
+        turns.add_entity_optional(
+            grid.spawn(
+                &mut commands,
+                &grid::EntityKind::Unit,
+                &location,
+                root,
+                (
+                    Transform::from_translation(scale.translation(
+                        &grid,
+                        &location,
+                        unit::UNIT_Z_LAYER,
+                    )),
+                    team_1.clone(),
+                    unit::Facing::new(IVec2::Y),
+                    unit::Movement::new(rand.map().range(step_range.clone())),
+                    unit::Health::new(50),
+                    unit::Attacks::new(3, 10)
+                        .with_forced(forced::Forced::Push(2))
+                        .with_effect("blast"),
                 ),
-                0,
-            );
-        }
+            ),
+            0,
+        );
     }
 
-    let team_2_spaces = grid::selection::Shape::Square(IVec2::new(0, size.y - spawn_space.y), size);
-    for _ in 0..100 {
-        if let Some(location) = grid.nearest_empty(
-            &grid::EntityKind::Unit,
-            &team_2_spaces.random(rand.as_mut()),
-            &IVec2::ZERO,
-            team_2_spaces.clone(),
-        ) {
-            turns.add_entity_optional(
-                grid.spawn(
-                    &mut commands,
-                    &grid::EntityKind::Unit,
-                    &location,
-                    root,
-                    (
-                        Sprite {
-                            color: Color::linear_rgb(0.0, 0.0, 1.0),
-                            ..sprites.unit.sprite()
-                        },
-                        Transform::from_translation(cords::location_to_translation(
-                            &location,
-                            scale.scale(),
-                            1,
-                        )),
-                        unit::Unit { team: 2 },
-                        unit::Movement::new(rand::random_range(step_range.clone())),
-                        unit::Health::new(3),
-                        unit::Attacks::new(1, 1),
-                    ),
+    let team_2 = unit::Unit { team: 2 };
+    let team_2_spaces = team_1_spaces
+        .clone()
+        .translate(IVec2::new(0, size.y - spawn_space.y));
+    let team_2_locations = team_2_spaces.place(
+        rand.map(),
+        size,
+        100,
+        &grid::selection::Placement::Jittered,
+        |location| grid.can_enter(&grid::EntityKind::Unit, location),
+    );
+    for location in team_2_locations {
+        turns.add_entity_optional(
+            grid.spawn(
+                &mut commands,
+                &grid::EntityKind::Unit,
+                &location,
+                root,
+                (
+                    Transform::from_translation(scale.translation(
+                        &grid,
+                        &location,
+                        unit::UNIT_Z_LAYER,
+                    )),
+                    team_2.clone(),
+                    unit::Facing::new(IVec2::NEG_Y),
+                    unit::Movement::new(rand.map().range(step_range.clone())),
+                    unit::Health::new(3),
+                    unit::Attacks::new(1, 1),
                 ),
-                1,
-            );
-        }
+            ),
+            1,
+        );
+    }
+
+    // Objects are placed in the band between the two teams.
+    let middle = size.y / 2;
+    for x in 0..size.x {
+        let object = if x % 10 == 5 {
+            object::MapObject::Door { open: false }
+        } else if rand.map().ratio(1, 5) {
+            continue;
+        } else {
+            object::MapObject::Wall { health: 20 }
+        };
+        object::spawn_object(
+            &mut commands,
+            &mut grid,
+            root,
+            &scale,
+            &sprites,
+            &IVec2::new(x, middle),
+            object,
+        );
+    }
+    // Hazards are kept clear of the wall so they never block a gap.
+    let hazard_spaces = grid::selection::Shape::Difference(vec![
+        grid::selection::Shape::Square(
+            IVec2::new(0, spawn_space.y),
+            IVec2::new(size.x, size.y - spawn_space.y),
+        ),
+        grid::selection::Shape::Rect(IVec2::new(size.x / 2, middle), IVec2::new(size.x, 1)),
+    ]);
+    let hazard_locations = hazard_spaces.place(
+        rand.map(),
+        size,
+        16,
+        &grid::selection::Placement::Poisson(4.0),
+        |location| grid.can_enter(&grid::EntityKind::Object, location),
+    );
+    for (index, location) in hazard_locations.iter().enumerate() {
+        let object = match index % 5 {
+            0 => object::MapObject::Trap { damage: 2 },
+            1 => object::MapObject::Fire {
+                damage: 1,
+                turns: 4,
+            },
+            2 => object::MapObject::Herb { heal: 4 },
+            _ => object::MapObject::Barrel {
+                damage: 5,
+                radius: 2.0,
+            },
+        };
+        object::spawn_object(
+            &mut commands,
+            &mut grid,
+            root,
+            &scale,
+            &sprites,
+            location,
+            object,
+        );
+    }
+
+    // Monsters stand in the band between the two teams, getting in the way without taking a side.
+    let monsters = unit::Unit { team: 3 };
+    let monster_locations = hazard_spaces.place(
+        rand.map(),
+        size,
+        6,
+        &grid::selection::Placement::Poisson(4.0),
+        |location| {
+            grid.can_enter(&grid::EntityKind::Unit, location)
+                && grid.can_enter(&grid::EntityKind::Object, location)
+        },
+    );
+    for location in monster_locations {
+        turns.add_entity_optional(
+            grid.spawn(
+                &mut commands,
+                &grid::EntityKind::Unit,
+                &location,
+                root,
+                (
+                    Transform::from_translation(scale.translation(
+                        &grid,
+                        &location,
+                        unit::UNIT_Z_LAYER,
+                    )),
+                    monsters.clone(),
+                    unit::Facing::new(IVec2::NEG_Y),
+                    unit::Movement::new(rand.map().range(step_range.clone())),
+                    unit::Health::new(10).with_shield(3),
+                    unit::Attacks::new(2, 1),
+                ),
+            ),
+            2,
+        );
     }
 
+    // Each player team can rearrange its units within its spawn space before the battle begins.
+    // Computer teams keep the placement they were given.
+    let mut zones = vec![
+        deploy::DeployZone::new(team_1.team, team_1_spaces, IVec2::Y),
+        deploy::DeployZone::new(team_2.team, team_2_spaces, IVec2::NEG_Y),
+    ];
+    for zone in zones.iter_mut() {
+        zone.confirmed = teams
+            .get(zone.team)
+            .is_some_and(|team| team.controller == team::Controller::Computer);
+    }
+    let deployment = deploy::Deployment::new(zones);
+
     commands.entity(root).insert((grid, scale, turns));
+    if !deployment.is_ready() {
+        commands.entity(root).insert(deployment);
+    }
 }
@@ -0,0 +1,472 @@
+use bevy::ecs::relationship::Relationship;
+use bevy::prelude::*;
+
+use super::effect;
+use super::forced;
+use super::game;
+use super::grid;
+use super::unit;
+use crate::random::RandomSource;
+use crate::theme::Textures;
+
+pub const OBJECT_Z_LAYER: i32 = 0;
+// Extra pathfinding cost so units prefer to walk around hazards.
+const HAZARD_COST: u32 = 4;
+// Extra pathfinding cost for the turn spent opening a closed door.
+const DOOR_COST: u32 = 1;
+// Spaces units caught in a barrel's blast are pushed away from it.
+const BLAST_PUSH: u32 = 1;
+
+pub fn plugin(app: &mut App) {
+    app.add_observer(trigger_on_enter);
+    app.add_observer(explode_on_impact);
+    app.add_observer(do_explode);
+    app.add_observer(toggle_door);
+    app.add_observer(burn_fire);
+
+    app.register_type::<MapObject>();
+}
+
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+#[require(Transform, Name::new("MapObject"))]
+// An object placed on the grid that units interact with.
+pub enum MapObject {
+    // Damages the first unit to enter it and is then removed.
+    Trap { damage: u32 },
+    // Heals the first unit to enter it and is then removed.
+    Herb { heal: u32 },
+    // Damages units in or entering it, and spreads to neighbouring spaces while it burns.
+    Fire { damage: u32, turns: u32 },
+    // Explodes when attacked, damaging everything within the radius and pushing units back.
+    Barrel { damage: u32, radius: f32 },
+    // Closed doors are opened by units moving through them.
+    Door { open: bool },
+    // Blocks movement until destroyed.
+    Wall { health: u32 },
+}
+
+impl MapObject {
+    // Returns true if units can not enter the space holding this object.
+    pub fn blocking(&self) -> bool {
+        matches!(self, MapObject::Barrel { .. } | MapObject::Wall { .. })
+    }
+
+    // Returns the additional pathfinding cost for entering the space holding this object.
+    pub fn cost(&self) -> u32 {
+        match self {
+            MapObject::Trap { .. } | MapObject::Fire { .. } => HAZARD_COST,
+            MapObject::Door { open: false } => DOOR_COST,
+            _ => 0,
+        }
+    }
+
+    pub fn is_closed_door(&self) -> bool {
+        matches!(self, MapObject::Door { open: false })
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            MapObject::Trap { .. } => Color::srgb(0.5, 0.2, 0.6),
+            MapObject::Herb { .. } => Color::srgb(0.3, 0.8, 0.3),
+            MapObject::Fire { .. } => Color::srgb(1.0, 0.5, 0.0),
+            MapObject::Barrel { .. } => Color::srgb(0.6, 0.35, 0.1),
+            MapObject::Door { open: false } => Color::srgb(0.8, 0.7, 0.3),
+            MapObject::Door { open: true } => Color::srgba(0.8, 0.7, 0.3, 0.3),
+            MapObject::Wall { .. } => Color::srgb(0.4, 0.4, 0.4),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MapObject::Trap { .. } => "Trap",
+            MapObject::Herb { .. } => "Herb",
+            MapObject::Fire { .. } => "Fire",
+            MapObject::Barrel { .. } => "Barrel",
+            MapObject::Door { .. } => "Door",
+            MapObject::Wall { .. } => "Wall",
+        }
+    }
+
+    // A short description of the object, shown when hovering over it.
+    pub fn describe(&self) -> String {
+        match self {
+            MapObject::Trap { damage } => format!("Trap, {} damage", damage),
+            MapObject::Herb { heal } => format!("Herb, heals {}", heal),
+            MapObject::Fire { damage, turns } => {
+                format!("Fire, {} damage for {} turns", damage, turns)
+            }
+            MapObject::Barrel { damage, radius } => {
+                format!("Barrel, explodes for {} damage within {}", damage, radius)
+            }
+            MapObject::Door { open: false } => "Closed door".to_string(),
+            MapObject::Door { open: true } => "Open door".to_string(),
+            MapObject::Wall { .. } => "Wall".to_string(),
+        }
+    }
+
+    // Objects are drawn as a tinted tile slightly smaller than their space.
+    pub fn sprite(&self, textures: &Textures) -> Sprite {
+        Sprite {
+            color: self.color(),
+            custom_size: Some(textures.tile.scale() * 0.8),
+            ..textures.tile.sprite()
+        }
+    }
+}
+
+// Spawns a map object into the grid, applying its blocking and cost to the space. Returns the spawned entity if successful.
+pub fn spawn_object(
+    commands: &mut Commands,
+    grid: &mut grid::Grid,
+    grid_entity: Entity,
+    scale: &grid::GridScale,
+    textures: &Textures,
+    location: &IVec2,
+    object: MapObject,
+) -> Option<Entity> {
+    if object.blocking() && grid.get_entity(&grid::EntityKind::Unit, location).is_some() {
+        return None;
+    }
+    let translation = scale.translation(grid, location, OBJECT_Z_LAYER);
+    let entity = grid.spawn(
+        commands,
+        &grid::EntityKind::Object,
+        location,
+        grid_entity,
+        (
+            object.sprite(textures),
+            Transform::from_translation(translation),
+            object.clone(),
+        ),
+    )?;
+    grid.set_blocking(location, object.blocking());
+    grid.set_cost(location, object.cost());
+    if let MapObject::Wall { health } = object {
+        commands.entity(entity).insert(unit::Health::new(health));
+    }
+    Some(entity)
+}
+
+fn trigger_on_enter(
+    trigger: On<game::EnterSpace>,
+    mut commands: Commands,
+    owner_query: Query<&grid::GridOwner>,
+    grid_query: Query<&grid::Grid>,
+    object_query: Query<&MapObject>,
+    mut health_query: Query<&mut unit::Health>,
+) {
+    let Ok(owner) = owner_query.get(trigger.event_target()) else {
+        return;
+    };
+    let Ok(grid) = grid_query.get(owner.get()) else {
+        return;
+    };
+    if let Some(object_entity) =
+        grid.get_entity(&grid::EntityKind::Object, trigger.event().location())
+        && let Ok(mut health) = health_query.get_mut(trigger.event_target())
+    {
+        match object_query.get(object_entity) {
+            Ok(MapObject::Trap { damage }) => {
+                effect::damage(
+                    &mut commands,
+                    trigger.event_target(),
+                    &mut health,
+                    *damage,
+                    false,
+                );
+                commands.entity(object_entity).despawn();
+            }
+            Ok(MapObject::Herb { heal }) => {
+                effect::heal(&mut commands, trigger.event_target(), &mut health, *heal);
+                commands.entity(object_entity).despawn();
+            }
+            Ok(MapObject::Fire { damage, .. }) => effect::damage(
+                &mut commands,
+                trigger.event_target(),
+                &mut health,
+                *damage,
+                false,
+            ),
+            _ => {}
+        }
+    }
+}
+
+#[derive(EntityEvent, Clone, Debug, Reflect)]
+pub struct Explode {
+    entity: Entity,
+}
+
+// Barrels explode once an attack lands on them, so projectiles set them off when they arrive.
+fn explode_on_impact(
+    trigger: On<effect::Impact>,
+    mut commands: Commands,
+    object_query: Query<&MapObject>,
+) {
+    let target = trigger.event_target();
+    if !trigger.event().hit() {
+        return;
+    }
+    if let Ok(MapObject::Barrel { .. }) = object_query.get(target) {
+        commands.trigger(Explode { entity: target });
+    }
+}
+
+fn do_explode(
+    trigger: On<Explode>,
+    mut commands: Commands,
+    object_query: Query<(
+        &MapObject,
+        &grid::GridLocation,
+        &grid::GridOwner,
+        &Transform,
+    )>,
+    grid_query: Query<&grid::Grid>,
+    mut health_query: Query<&mut unit::Health>,
+) {
+    let entity = trigger.event_target();
+    let Ok((MapObject::Barrel { damage, radius }, location, owner, transform)) =
+        object_query.get(entity)
+    else {
+        return;
+    };
+    commands.trigger(effect::Effect::Emit(
+        "explosion".to_string(),
+        transform.translation.truncate(),
+    ));
+    let Ok(grid) = grid_query.get(owner.get()) else {
+        commands.entity(entity).despawn();
+        return;
+    };
+    let blast = grid::selection::Shape::Circle(location.location().as_vec2(), *radius);
+    for (_, target) in grid.entities_within(&grid::EntityKind::Unit, blast.clone()) {
+        if let Ok(mut health) = health_query.get_mut(target) {
+            effect::damage(&mut commands, target, &mut health, *damage, false);
+        }
+        // Pushed away while the barrel is still there to push them from.
+        commands.trigger(forced::ForcedMove::new(
+            target,
+            entity,
+            forced::Forced::Push(BLAST_PUSH),
+        ));
+    }
+    // Removed before the barrels it sets off explode, so their blasts do not set it off again.
+    commands.entity(entity).despawn();
+    for (_, target) in grid.entities_within(&grid::EntityKind::Object, blast) {
+        if target == entity {
+            continue;
+        }
+        // Barrels caught in the blast explode in turn.
+        if let Ok((MapObject::Barrel { .. }, _, _, _)) = object_query.get(target) {
+            commands.trigger(Explode { entity: target });
+        } else if let Ok(mut health) = health_query.get_mut(target) {
+            effect::damage(&mut commands, target, &mut health, *damage, false);
+        }
+    }
+}
+
+#[derive(EntityEvent, Clone, Debug, Reflect)]
+pub struct ToggleDoor {
+    entity: Entity,
+}
+
+impl ToggleDoor {
+    pub fn new(entity: Entity) -> Self {
+        ToggleDoor { entity }
+    }
+}
+
+fn toggle_door(
+    trigger: On<ToggleDoor>,
+    mut query: Query<(
+        &mut MapObject,
+        &mut Sprite,
+        &grid::GridLocation,
+        &grid::GridOwner,
+    )>,
+    mut grid_query: Query<&mut grid::Grid>,
+) {
+    if let Ok((mut object, mut sprite, location, owner)) = query.get_mut(trigger.event_target())
+        && let Ok(mut grid) = grid_query.get_mut(owner.get())
+        && let MapObject::Door { open } = object.as_mut()
+    {
+        // A door can not be closed on a unit standing in it.
+        if *open
+            && grid
+                .get_entity(&grid::EntityKind::Unit, location.location())
+                .is_some()
+        {
+            return;
+        }
+        *open = !*open;
+        grid.set_cost(location.location(), object.cost());
+        sprite.color = object.color();
+    }
+}
+
+fn burn_fire(
+    trigger: On<game::TurnStart>,
+    mut commands: Commands,
+    textures: Res<Textures>,
+    mut rand: ResMut<RandomSource>,
+    mut grid_query: Query<(&mut grid::Grid, &grid::GridScale)>,
+    mut fire_query: Query<(
+        Entity,
+        &mut MapObject,
+        &grid::GridLocation,
+        &grid::GridOwner,
+    )>,
+    mut health_query: Query<&mut unit::Health>,
+) {
+    let grid_entity = trigger.event_target();
+    let Ok((mut grid, scale)) = grid_query.get_mut(grid_entity) else {
+        return;
+    };
+    let mut spreads = Vec::new();
+    for (entity, mut object, location, owner) in fire_query.iter_mut() {
+        if owner.get() != grid_entity {
+            continue;
+        }
+        if let MapObject::Fire { damage, turns } = object.as_mut() {
+            if let Some(unit) = grid.get_entity(&grid::EntityKind::Unit, location.location())
+                && let Ok(mut health) = health_query.get_mut(unit)
+            {
+                effect::damage(&mut commands, unit, &mut health, *damage, false);
+            }
+            *turns = turns.saturating_sub(1);
+            if *turns == 0 {
+                commands.entity(entity).despawn();
+                continue;
+            }
+            for direction in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+                if rand.combat().ratio(1, 4) {
+                    spreads.push((
+                        location.location() + direction,
+                        MapObject::Fire {
+                            damage: *damage,
+                            turns: *turns,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+    for (location, fire) in spreads {
+        if grid.can_enter(&grid::EntityKind::Object, &location) {
+            spawn_object(
+                &mut commands,
+                &mut grid,
+                grid_entity,
+                scale,
+                &textures,
+                &location,
+                fire,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct Exploded(Vec<Entity>);
+
+    fn count_explosions(trigger: On<Explode>, mut exploded: ResMut<Exploded>) {
+        exploded.0.push(trigger.event_target());
+    }
+
+    #[test]
+    fn test_chain_explosion() {
+        let mut app = App::new();
+        app.add_plugins(grid::plugin);
+        app.init_resource::<Exploded>();
+        app.add_observer(count_explosions);
+        app.add_observer(do_explode);
+
+        let world = app.world_mut();
+        let grid_entity = world.spawn_empty().id();
+        let mut grid = grid::Grid::new(IVec2::new(4, 4));
+        let barrel = MapObject::Barrel {
+            damage: 1,
+            radius: 1.5,
+        };
+        let mut commands = world.commands();
+        let [first, second] = [IVec2::new(1, 1), IVec2::new(2, 1)].map(|location| {
+            grid.spawn(
+                &mut commands,
+                &grid::EntityKind::Object,
+                &location,
+                grid_entity,
+                barrel.clone(),
+            )
+            .unwrap()
+        });
+        world.flush();
+        world.entity_mut(grid_entity).insert(grid);
+
+        world.trigger(Explode { entity: first });
+        world.flush();
+
+        // Each barrel sets off the other once, and both are removed from the grid.
+        assert_eq!(world.resource::<Exploded>().0, vec![first, second]);
+        assert!(world.get_entity(first).is_err());
+        assert!(world.get_entity(second).is_err());
+        let grid = world.get::<grid::Grid>(grid_entity).unwrap();
+        assert_eq!(
+            grid.get_entity(&grid::EntityKind::Object, &IVec2::new(1, 1)),
+            None
+        );
+        assert_eq!(
+            grid.get_entity(&grid::EntityKind::Object, &IVec2::new(2, 1)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_herb_heals_and_is_removed() {
+        let mut app = App::new();
+        app.add_plugins(grid::plugin);
+        app.add_observer(trigger_on_enter);
+
+        let world = app.world_mut();
+        let grid_entity = world.spawn_empty().id();
+        let mut grid = grid::Grid::new(IVec2::new(4, 4));
+        let location = IVec2::new(1, 1);
+        let mut commands = world.commands();
+        let herb = grid
+            .spawn(
+                &mut commands,
+                &grid::EntityKind::Object,
+                &location,
+                grid_entity,
+                MapObject::Herb { heal: 4 },
+            )
+            .unwrap();
+        let unit = grid
+            .spawn(
+                &mut commands,
+                &grid::EntityKind::Unit,
+                &location,
+                grid_entity,
+                unit::Health {
+                    current: 3,
+                    max: 10,
+                    shield: 0,
+                },
+            )
+            .unwrap();
+        world.flush();
+        world.entity_mut(grid_entity).insert(grid);
+
+        world.trigger(game::EnterSpace::new(unit, location));
+        world.flush();
+
+        assert_eq!(world.get::<unit::Health>(unit).unwrap().current, 7);
+        assert!(world.get_entity(herb).is_err());
+        let grid = world.get::<grid::Grid>(grid_entity).unwrap();
+        assert_eq!(grid.get_entity(&grid::EntityKind::Object, &location), None);
+    }
+}
@@ -0,0 +1,286 @@
+use bevy::ecs::relationship::Relationship;
+use bevy::prelude::*;
+
+use super::grid;
+use super::hud;
+use super::pick;
+use super::team;
+use super::unit;
+use super::zone;
+use crate::util::cords;
+
+// Drawn above the tiles and below objects and units.
+const OVERLAY_Z: f32 = -0.5;
+const MOVE_COLOR: Color = Color::srgba(0.2, 0.4, 1.0, 0.35);
+const ATTACK_COLOR: Color = Color::srgba(1.0, 0.2, 0.2, 0.35);
+const PATH_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
+const CONTESTED_COLOR: Color = Color::srgb(1.0, 0.0, 0.0);
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(RangeOverlay::default());
+
+    app.add_systems(
+        Update,
+        (update_ranges, update_path, draw_path)
+            .chain()
+            .after(hud::select_unit),
+    );
+
+    app.register_type::<RangeOverlay>();
+}
+
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+#[reflect(Resource)]
+// Where the selected unit can move and attack, and the path it would take to the hovered space.
+pub struct RangeOverlay {
+    pub unit: Option<Entity>,
+    pub reachable: Vec<IVec2>,
+    pub attackable: Vec<IVec2>,
+    pub path: Vec<IVec2>,
+    // Spaces along the path where leaving an enemy's zone of control gives it a free attack.
+    pub contested: Vec<IVec2>,
+}
+
+#[derive(Component)]
+// A tinted space of the range overlay.
+struct OverlayCell;
+
+// The spaces controlled by enemies of the unit, which cost extra movement to leave.
+fn hostile_zone(
+    teams: &team::Teams,
+    team: u32,
+    units: &Query<(Entity, &unit::Unit, &grid::GridLocation)>,
+) -> zone::Zone {
+    zone::Zone::hostile_to(
+        teams,
+        team,
+        units
+            .iter()
+            .map(|(entity, unit, location)| (entity, unit.team, *location.location())),
+    )
+}
+
+// The spaces an attack reaches from any of the reachable spaces, leaving out the reachable spaces themselves.
+fn attackable(grid: &grid::Grid, reachable: &[IVec2], range: f32) -> Vec<IVec2> {
+    let size = grid.size();
+    let mut seen: Vec<bool> = (0..grid.spaces() as usize)
+        .map(|index| reachable.contains(&cords::index_to_location(&size, index)))
+        .collect();
+    let mut spaces = Vec::new();
+    for from in reachable {
+        for location in grid.attackable(from, range) {
+            let index = cords::location_to_index(&size, &location);
+            if !seen[index] {
+                seen[index] = true;
+                spaces.push(location);
+            }
+        }
+    }
+    spaces
+}
+
+// The spaces along a path where leaving an enemy's zone of control gives it a free attack.
+fn contested(zone: &zone::Zone, rules: &zone::ZoneOfControl, path: &[IVec2]) -> Vec<IVec2> {
+    if !rules.opportunity_attacks {
+        return Vec::new();
+    }
+    path.windows(2)
+        .filter(|pair| !zone.opportunities(&pair[0], &pair[1]).is_empty())
+        .map(|pair| pair[0])
+        .collect()
+}
+
+// Rebuilds the tinted spaces when a different unit is selected or anything on its grid moves.
+fn update_ranges(
+    mut commands: Commands,
+    selection: Res<hud::Selection>,
+    teams: Res<team::Teams>,
+    rules: Res<zone::ZoneOfControl>,
+    mut overlay: ResMut<RangeOverlay>,
+    changed_query: Query<(), Changed<grid::Grid>>,
+    grid_query: Query<(&grid::Grid, &grid::GridScale, &GlobalTransform)>,
+    unit_query: Query<(
+        &grid::GridLocation,
+        &grid::GridOwner,
+        &unit::Unit,
+        &unit::Movement,
+        Option<&unit::Attacks>,
+    )>,
+    units: Query<(Entity, &unit::Unit, &grid::GridLocation)>,
+    cell_query: Query<Entity, With<OverlayCell>>,
+) {
+    if overlay.unit == selection.selected && changed_query.is_empty() && !teams.is_changed() {
+        return;
+    }
+    for entity in cell_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    overlay.unit = selection.selected;
+    overlay.reachable.clear();
+    overlay.attackable.clear();
+    let Some((location, owner, unit, movement, attacks)) = selection
+        .selected
+        .and_then(|entity| unit_query.get(entity).ok())
+    else {
+        return;
+    };
+    let Ok((grid, scale, transform)) = grid_query.get(owner.get()) else {
+        return;
+    };
+    let zone = hostile_zone(&teams, unit.team, &units);
+    overlay.reachable = grid.reachable(
+        &grid::EntityKind::Unit,
+        location.location(),
+        movement.spaces,
+        |location| zone.leave_cost(&rules, location),
+    );
+    overlay.attackable = attacks.map_or(Vec::new(), |attacks| {
+        attackable(grid, &overlay.reachable, attacks.range)
+    });
+    let size = scale.scale().as_vec2();
+    let origin = transform.translation().xy();
+    for (spaces, color) in [
+        (&overlay.reachable, MOVE_COLOR),
+        (&overlay.attackable, ATTACK_COLOR),
+    ] {
+        for position in scale.iter_in_scale(grid, origin, spaces.iter().copied()) {
+            commands.spawn((
+                Name::new("Overlay"),
+                OverlayCell,
+                Sprite::from_color(color, size),
+                Transform::from_translation(position.extend(OVERLAY_Z)),
+            ));
+        }
+    }
+}
+
+// Plans the path the selected unit would take to the hovered space.
+fn update_path(
+    hovered: Res<pick::Hovered>,
+    teams: Res<team::Teams>,
+    rules: Res<zone::ZoneOfControl>,
+    mut overlay: ResMut<RangeOverlay>,
+    grid_query: Query<&grid::Grid>,
+    unit_query: Query<(
+        &grid::GridLocation,
+        &grid::GridOwner,
+        &unit::Unit,
+        &unit::Movement,
+    )>,
+    units: Query<(Entity, &unit::Unit, &grid::GridLocation)>,
+) {
+    if !hovered.is_changed() && !overlay.is_changed() {
+        return;
+    }
+    let (path, contested) = match (
+        overlay.unit.and_then(|entity| unit_query.get(entity).ok()),
+        hovered.space,
+    ) {
+        (Some((location, owner, unit, movement)), Some((grid_entity, target)))
+            if owner.get() == grid_entity && *location.location() != target =>
+        {
+            grid_query
+                .get(grid_entity)
+                .map_or((Vec::new(), Vec::new()), |grid| {
+                    let zone = hostile_zone(&teams, unit.team, &units);
+                    let path = grid.a_star_weighted_to(
+                        &grid::EntityKind::Unit,
+                        location.location(),
+                        &target,
+                        movement.spaces,
+                        |location| zone.leave_cost(&rules, location),
+                    );
+                    let contested = contested(&zone, &rules, &path);
+                    (path, contested)
+                })
+        }
+        _ => (Vec::new(), Vec::new()),
+    };
+    if overlay.path != path || overlay.contested != contested {
+        overlay.path = path;
+        overlay.contested = contested;
+    }
+}
+
+// Draws the planned path as a line ending in an arrow, circling the spaces where enemies get a free attack.
+fn draw_path(
+    mut gizmos: Gizmos,
+    overlay: Res<RangeOverlay>,
+    unit_query: Query<&grid::GridOwner>,
+    grid_query: Query<(&grid::Grid, &grid::GridScale, &GlobalTransform)>,
+) {
+    let Some((grid, scale, transform)) = overlay
+        .unit
+        .and_then(|entity| unit_query.get(entity).ok())
+        .and_then(|owner| grid_query.get(owner.get()).ok())
+    else {
+        return;
+    };
+    let points: Vec<Vec2> = scale
+        .iter_in_scale(
+            grid,
+            transform.translation().xy(),
+            overlay.path.iter().copied(),
+        )
+        .collect();
+    if let [.., from, to] = points.as_slice() {
+        gizmos.linestrip_2d(points[..points.len() - 1].iter().copied(), PATH_COLOR);
+        gizmos.arrow_2d(*from, *to, PATH_COLOR);
+    }
+    let radius = scale.scale().x as f32 / 4.0;
+    for position in scale.iter_in_scale(
+        grid,
+        transform.translation().xy(),
+        overlay.contested.iter().copied(),
+    ) {
+        gizmos.circle_2d(position, radius, CONTESTED_COLOR);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attackable_skips_reachable() {
+        let grid = grid::Grid::new(IVec2::new(5, 5));
+        let reachable = vec![IVec2::new(2, 2), IVec2::new(3, 2)];
+        let mut spaces = attackable(&grid, &reachable, 1.0);
+        spaces.sort_by_key(|location| (location.x, location.y));
+
+        assert_eq!(
+            spaces,
+            vec![
+                IVec2::new(1, 2),
+                IVec2::new(2, 1),
+                IVec2::new(2, 3),
+                IVec2::new(3, 1),
+                IVec2::new(3, 3),
+                IVec2::new(4, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contested_marks_spaces_left() {
+        let enemy = Entity::from_bits(1);
+        let zone = zone::Zone::hostile_to(
+            &team::Teams::default(),
+            1,
+            [(enemy, 2, IVec2::new(2, 2))].into_iter(),
+        );
+        let path = vec![
+            IVec2::new(1, 2),
+            IVec2::new(1, 3),
+            IVec2::new(0, 3),
+            IVec2::new(0, 4),
+        ];
+        let mut rules = zone::ZoneOfControl::default();
+
+        // Only stepping from a controlled space to one the enemy does not control is contested.
+        assert_eq!(contested(&zone, &rules, &path), vec![IVec2::new(1, 3)]);
+
+        rules.opportunity_attacks = false;
+        assert!(contested(&zone, &rules, &path).is_empty());
+    }
+}
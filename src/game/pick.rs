@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::camera;
+use super::grid;
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(Hovered::default());
+
+    app.add_systems(PreUpdate, pick_space);
+
+    app.register_type::<Hovered>();
+}
+
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+#[reflect(Resource)]
+// The grid and the location in it under the cursor.
+pub struct Hovered {
+    pub space: Option<(Entity, IVec2)>,
+}
+
+impl Hovered {
+    // Returns the entity of a specific kind in the hovered space.
+    pub fn entity(
+        &self,
+        kind: &grid::EntityKind,
+        grid_query: &Query<&grid::Grid>,
+    ) -> Option<Entity> {
+        let (grid_entity, location) = self.space?;
+        grid_query
+            .get(grid_entity)
+            .ok()
+            .and_then(|grid| grid.get_entity(kind, &location))
+    }
+}
+
+// Finds the space under the cursor, looking through the camera into each grid.
+fn pick_space(
+    mut hovered: ResMut<Hovered>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<camera::ControlledCamera>>,
+    grid_query: Query<(Entity, &grid::Grid, &grid::GridScale, &GlobalTransform)>,
+) {
+    let cursor = match (window_query.single(), camera_query.single()) {
+        (Ok(window), Ok((camera, transform))) => camera::cursor_position(window, camera, transform),
+        _ => None,
+    };
+    let space = cursor.and_then(|cursor| {
+        grid_query
+            .iter()
+            .find_map(|(entity, grid, scale, transform)| {
+                let local = cursor - transform.translation().truncate();
+                scale
+                    .location(grid, &local)
+                    .map(|location| (entity, location))
+            })
+    });
+    if hovered.space != space {
+        hovered.space = space;
+    }
+}
@@ -0,0 +1,335 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::ecs::entity::EntityHashMap;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use bevy::scene::serde::SceneDeserializer;
+use serde::de::DeserializeSeed;
+
+use super::game;
+use super::grid;
+use super::object;
+use super::unit;
+use crate::random::RandomSource;
+use crate::random::RandomState;
+use crate::theme::Textures;
+use crate::util::cords;
+
+const SAVE_PATH: &str = "saves/battle.scn.ron";
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            save_to_file.run_if(input_just_pressed(KeyCode::F5)),
+            load_from_file.run_if(input_just_pressed(KeyCode::F9)),
+        ),
+    );
+
+    app.register_type::<BattleSave>();
+}
+
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource)]
+// Everything about a battle that is not stored on the saved units and objects.
+pub struct BattleSave {
+    pub size: IVec2,
+    // Elevation of every space, in index order.
+    pub heights: Vec<i32>,
+    // Turn order using the entity ids from the save, remapped when loading.
+    pub turn_order: Vec<Vec<Entity>>,
+    pub turn_index: usize,
+    pub round: u32,
+    pub random: RandomState,
+}
+
+// Saves the battle on the grid entity into the Bevy scene format.
+// Tiles are not saved, they are rebuilt from the grid when loading.
+pub fn save(world: &mut World, grid_entity: Entity) -> Result<String> {
+    let entity = world.entity(grid_entity);
+    let grid = entity.get::<grid::Grid>().ok_or("Missing grid")?;
+    let turns = entity
+        .get::<game::TurnOrder>()
+        .ok_or("Missing turn order")?;
+    let owned = entity
+        .get::<grid::GridOwned>()
+        .map_or(Vec::new(), |owned| owned.iter().collect::<Vec<_>>());
+    let saved: Vec<Entity> = owned
+        .into_iter()
+        .filter(|entity| {
+            world
+                .get::<grid::GridLocation>(*entity)
+                .is_some_and(|location| *location.kind() != grid::EntityKind::Tile)
+        })
+        .collect();
+
+    let size = grid.size();
+    let battle = BattleSave {
+        size,
+        heights: (0..grid.spaces() as usize)
+            .map(|index| grid.height(&cords::index_to_location(&size, index)))
+            .collect(),
+        turn_order: turns
+            .order
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .filter(|entity| saved.contains(entity))
+                    .copied()
+                    .collect()
+            })
+            .collect(),
+        turn_index: turns.index,
+        round: turns.round,
+        random: world.resource::<RandomSource>().snapshot(),
+    };
+
+    let mut scene = DynamicSceneBuilder::from_world(world)
+        .deny_all()
+        .allow_component::<grid::GridLocation>()
+        .allow_component::<unit::Unit>()
+        .allow_component::<unit::Movement>()
+        .allow_component::<unit::Health>()
+        .allow_component::<unit::Attacks>()
+        .allow_component::<unit::Facing>()
+        .allow_component::<object::MapObject>()
+        .extract_entities(saved.into_iter())
+        .build();
+    scene.resources.push(Box::new(battle));
+
+    let registry = world.resource::<AppTypeRegistry>().read();
+    Ok(scene.serialize(&registry)?)
+}
+
+// Replaces any battle in the world with one read from the Bevy scene format. Returns the new grid entity.
+pub fn load(world: &mut World, text: &str) -> Result<Entity> {
+    let scene = {
+        let registry = world.resource::<AppTypeRegistry>().read();
+        let mut deserializer = ron::de::Deserializer::from_str(text)?;
+        SceneDeserializer {
+            type_registry: &registry,
+        }
+        .deserialize(&mut deserializer)?
+    };
+
+    let existing: Vec<Entity> = world
+        .query_filtered::<Entity, With<grid::Grid>>()
+        .iter(world)
+        .collect();
+    for grid_entity in existing {
+        let owned = world
+            .get::<grid::GridOwned>(grid_entity)
+            .map_or(Vec::new(), |owned| owned.iter().collect::<Vec<_>>());
+        for entity in owned {
+            world.despawn(entity);
+        }
+        world.despawn(grid_entity);
+    }
+
+    let mut entity_map = EntityHashMap::default();
+    scene.write_to_world(world, &mut entity_map)?;
+    let battle = world
+        .remove_resource::<BattleSave>()
+        .ok_or("Missing battle")?;
+    world.resource_mut::<RandomSource>().restore(&battle.random);
+
+    let mut grid = grid::Grid::new(battle.size);
+    for (index, height) in battle.heights.iter().enumerate() {
+        grid.set_height(&cords::index_to_location(&battle.size, index), *height);
+    }
+    // Groups are kept even when empty so the turn index still lines up.
+    let turns = game::TurnOrder {
+        order: battle
+            .turn_order
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .filter_map(|entity| entity_map.get(entity).copied())
+                    .collect()
+            })
+            .collect(),
+        index: battle.turn_index,
+        round: battle.round,
+    };
+
+    let grid_entity = world.spawn_empty().id();
+    let scale = match world.get_resource::<Textures>() {
+        Some(textures) => grid::GridScale::new(textures.tile.scale().as_ivec2()),
+        None => grid::GridScale::new(IVec2::ONE),
+    };
+    for entity in entity_map.values().copied() {
+        let Some(location) = world.get::<grid::GridLocation>(entity).cloned() else {
+            continue;
+        };
+        if !grid.place(&location, entity) {
+            world.despawn(entity);
+            continue;
+        }
+        let mut z = unit::UNIT_Z_LAYER;
+        if let Some(map_object) = world.get::<object::MapObject>(entity) {
+            grid.set_blocking(location.location(), map_object.blocking());
+            grid.set_cost(location.location(), map_object.cost());
+            z = object::OBJECT_Z_LAYER;
+        }
+        let translation = scale.translation(&grid, location.location(), z);
+        world.entity_mut(entity).insert((
+            grid::GridOwner::new(grid_entity),
+            Transform::from_translation(translation),
+        ));
+        restore_sprite(world, entity);
+    }
+    world.entity_mut(grid_entity).insert((scale, turns, grid));
+    Ok(grid_entity)
+}
+
+// Sprites are not saved, so they are rebuilt from the loaded components.
+// Units are styled by their team as they are added.
+fn restore_sprite(world: &mut World, entity: Entity) {
+    let Some(textures) = world.get_resource::<Textures>() else {
+        return;
+    };
+    let Some(map_object) = world.get::<object::MapObject>(entity) else {
+        return;
+    };
+    let visuals = (map_object.sprite(textures), bevy::sprite::Anchor::CENTER);
+    world.entity_mut(entity).insert(visuals);
+}
+
+fn save_to_file(world: &mut World) {
+    let Ok(grid_entity) = world
+        .query_filtered::<Entity, With<grid::Grid>>()
+        .single(world)
+    else {
+        return;
+    };
+    let result = save(world, grid_entity).and_then(|text| {
+        if let Some(parent) = Path::new(SAVE_PATH).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(fs::write(SAVE_PATH, text)?)
+    });
+    match result {
+        Ok(()) => info!("Saved battle to {}", SAVE_PATH),
+        Err(error) => warn!("Failed to save battle: {}", error),
+    }
+}
+
+fn load_from_file(world: &mut World) {
+    let result = fs::read_to_string(SAVE_PATH)
+        .map_err(BevyError::from)
+        .and_then(|text| load(world, &text));
+    match result {
+        Ok(_) => info!("Loaded battle from {}", SAVE_PATH),
+        Err(error) => warn!("Failed to load battle: {}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        {
+            let mut registry = registry.write();
+            registry.register::<BattleSave>();
+            registry.register::<grid::GridLocation>();
+            registry.register::<unit::Unit>();
+            registry.register::<unit::Movement>();
+            registry.register::<unit::Health>();
+            registry.register::<unit::Attacks>();
+            registry.register::<unit::Facing>();
+            registry.register::<object::MapObject>();
+        }
+        world.insert_resource(registry);
+        world.insert_resource(RandomSource::new(12345));
+        world
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut world = world();
+        let root = world.spawn_empty().id();
+        let mut grid = grid::Grid::new(IVec2::new(6, 4));
+        grid.set_height(&IVec2::new(3, 2), 2);
+        let mut turns = game::TurnOrder::default();
+        let mut commands = world.commands();
+        let unit = grid
+            .spawn(
+                &mut commands,
+                &grid::EntityKind::Unit,
+                &IVec2::new(1, 1),
+                root,
+                (
+                    unit::Unit { team: 2 },
+                    unit::Health {
+                        current: 4,
+                        max: 9,
+                        shield: 2,
+                    },
+                    unit::Facing::new(IVec2::NEG_X),
+                ),
+            )
+            .unwrap();
+        let wall = grid
+            .spawn(
+                &mut commands,
+                &grid::EntityKind::Object,
+                &IVec2::new(4, 3),
+                root,
+                object::MapObject::Wall { health: 7 },
+            )
+            .unwrap();
+        grid.set_blocking(&IVec2::new(4, 3), true);
+        turns.add_entity(unit, 1);
+        turns.index = 1;
+        turns.round = 3;
+        world.flush();
+        world.entity_mut(root).insert((grid, turns));
+        world.resource_mut::<RandomSource>().combat().range(0..100);
+
+        let text = save(&mut world, root).unwrap();
+        let expected: Vec<u32> = (0..5)
+            .map(|_| world.resource_mut::<RandomSource>().combat().range(0..100))
+            .collect();
+
+        let loaded = load(&mut world, &text).unwrap();
+        assert!(world.get_entity(root).is_err());
+        assert!(world.get_entity(unit).is_err());
+
+        let grid = world.get::<grid::Grid>(loaded).unwrap();
+        assert_eq!(grid.size(), IVec2::new(6, 4));
+        assert_eq!(grid.height(&IVec2::new(3, 2)), 2);
+        assert!(!grid.can_enter(&grid::EntityKind::Unit, &IVec2::new(4, 3)));
+        let loaded_unit = grid
+            .get_entity(&grid::EntityKind::Unit, &IVec2::new(1, 1))
+            .unwrap();
+        assert!(
+            grid.get_entity(&grid::EntityKind::Object, &IVec2::new(4, 3))
+                .is_some()
+        );
+
+        let turns = world.get::<game::TurnOrder>(loaded).unwrap();
+        assert_eq!(turns.order, vec![vec![], vec![loaded_unit]]);
+        assert_eq!(turns.index, 1);
+        assert_eq!(turns.round, 3);
+
+        let health = world.get::<unit::Health>(loaded_unit).unwrap();
+        assert_eq!((health.current, health.max, health.shield), (4, 9, 2));
+        assert_eq!(
+            world.get::<unit::Facing>(loaded_unit).unwrap().direction(),
+            &IVec2::NEG_X
+        );
+        assert_eq!(world.get::<unit::Unit>(loaded_unit).unwrap().team, 2);
+        assert!(world.get_entity(wall).is_err());
+
+        let results: Vec<u32> = (0..5)
+            .map(|_| world.resource_mut::<RandomSource>().combat().range(0..100))
+            .collect();
+        assert_eq!(results, expected);
+    }
+}
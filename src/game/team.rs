@@ -0,0 +1,317 @@
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use super::unit;
+use crate::theme::Textures;
+
+// Team colors, ordered so that neighbouring slots contrast strongly.
+const STANDARD_COLORS: [Color; 6] = [
+    Color::linear_rgb(1.0, 0.0, 0.0),
+    Color::linear_rgb(0.0, 0.0, 1.0),
+    Color::linear_rgb(0.0, 0.8, 0.0),
+    Color::linear_rgb(1.0, 0.8, 0.0),
+    Color::linear_rgb(0.6, 0.0, 0.8),
+    Color::linear_rgb(1.0, 0.4, 0.0),
+];
+// The Okabe-Ito colors, which stay distinct under the common forms of color blindness.
+const COLORBLIND_COLORS: [Color; 6] = [
+    Color::srgb(0.84, 0.37, 0.0),
+    Color::srgb(0.0, 0.45, 0.70),
+    Color::srgb(0.0, 0.62, 0.45),
+    Color::srgb(0.94, 0.89, 0.26),
+    Color::srgb(0.80, 0.47, 0.65),
+    Color::srgb(0.34, 0.71, 0.91),
+];
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(Teams::default());
+    app.add_observer(style_unit);
+
+    app.add_systems(
+        Update,
+        (
+            toggle_palette.run_if(input_just_pressed(KeyCode::F2)),
+            recolor_units.run_if(resource_changed::<Teams>),
+        )
+            .chain(),
+    );
+
+    app.register_type::<Teams>();
+    app.register_type::<Team>();
+    app.register_type::<Controller>();
+    app.register_type::<Stance>();
+    app.register_type::<Palette>();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+// Who chooses the actions of a team's units.
+pub enum Controller {
+    Player,
+    Computer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+// How the units of one team treat the units of another.
+pub enum Stance {
+    // Fight on the same side and never target each other.
+    Allied,
+    // Ignore each other, neither targeting each other nor controlling the spaces around themselves.
+    Neutral,
+    // Target each other and control the spaces around themselves.
+    Hostile,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+// The set of colors teams are drawn in.
+pub enum Palette {
+    #[default]
+    Standard,
+    ColorblindSafe,
+}
+
+impl Palette {
+    // Returns the color in a slot of the palette, wrapping around once every color is used.
+    pub fn color(&self, slot: usize) -> Color {
+        let colors = match self {
+            Palette::Standard => &STANDARD_COLORS,
+            Palette::ColorblindSafe => &COLORBLIND_COLORS,
+        };
+        colors[slot % colors.len()]
+    }
+}
+
+#[derive(Clone, Debug, Reflect)]
+pub struct Team {
+    pub id: u32,
+    pub name: String,
+    // Slot in the palette the team is drawn with.
+    pub color: usize,
+    pub controller: Controller,
+    // Name of the theme texture drawn for the team's units instead of the unit texture.
+    pub sprite: Option<String>,
+}
+
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource)]
+// Every team taking part in the battle, and how they treat each other.
+// Units always treat their own team as allied. Other pairs of teams use the default stance unless one was set.
+pub struct Teams {
+    teams: Vec<Team>,
+    // Stances between pairs of teams, stored with the lower team id first.
+    stances: Vec<(u32, u32, Stance)>,
+    pub default_stance: Stance,
+    pub palette: Palette,
+}
+
+impl Default for Teams {
+    // Two sides fighting it out, with monsters between them that neither side fights.
+    fn default() -> Self {
+        Teams::new()
+            .with_team(1, "Red", 0, Controller::Player)
+            .with_team(2, "Blue", 1, Controller::Computer)
+            .with_team(3, "Monsters", 2, Controller::Computer)
+            .with_sprite(3, "monster")
+            .with_stance(1, 3, Stance::Neutral)
+            .with_stance(2, 3, Stance::Neutral)
+    }
+}
+
+impl Teams {
+    // An empty registry where every team is hostile to every other, as in a free-for-all.
+    pub fn new() -> Self {
+        Teams {
+            teams: Vec::new(),
+            stances: Vec::new(),
+            default_stance: Stance::Hostile,
+            palette: Palette::default(),
+        }
+    }
+
+    // Adds a team, replacing any team with the same id.
+    pub fn with_team(
+        mut self,
+        id: u32,
+        name: impl Into<String>,
+        color: usize,
+        controller: Controller,
+    ) -> Self {
+        self.teams.retain(|team| team.id != id);
+        self.teams.push(Team {
+            id,
+            name: name.into(),
+            color,
+            controller,
+            sprite: None,
+        });
+        self
+    }
+
+    // Draws the units of a team with their own texture from the theme.
+    pub fn with_sprite(mut self, id: u32, name: impl Into<String>) -> Self {
+        if let Some(team) = self.teams.iter_mut().find(|team| team.id == id) {
+            team.sprite = Some(name.into());
+        }
+        self
+    }
+
+    pub fn with_stance(mut self, a: u32, b: u32, stance: Stance) -> Self {
+        self.set_stance(a, b, stance);
+        self
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Team> {
+        self.teams.iter().find(|team| team.id == id)
+    }
+
+    // Returns the color a team is drawn in. Unknown teams are drawn without a tint.
+    pub fn color(&self, id: u32) -> Color {
+        self.get(id)
+            .map_or(Color::WHITE, |team| self.palette.color(team.color))
+    }
+
+    // Sets how two teams treat each other. Stances always apply both ways.
+    pub fn set_stance(&mut self, a: u32, b: u32, stance: Stance) {
+        if a == b {
+            return;
+        }
+        let (low, high) = (a.min(b), a.max(b));
+        self.stances
+            .retain(|(first, second, _)| (*first, *second) != (low, high));
+        self.stances.push((low, high, stance));
+    }
+
+    pub fn stance(&self, a: u32, b: u32) -> Stance {
+        if a == b {
+            return Stance::Allied;
+        }
+        let (low, high) = (a.min(b), a.max(b));
+        self.stances
+            .iter()
+            .find(|(first, second, _)| (*first, *second) == (low, high))
+            .map_or(self.default_stance, |(_, _, stance)| *stance)
+    }
+
+    // Returns true if units of the first team should target units of the second.
+    pub fn is_hostile(&self, a: u32, b: u32) -> bool {
+        self.stance(a, b) == Stance::Hostile
+    }
+
+    pub fn is_allied(&self, a: u32, b: u32) -> bool {
+        self.stance(a, b) == Stance::Allied
+    }
+}
+
+// Units are drawn in their team's color when they are added, including units loaded from a save.
+fn style_unit(
+    trigger: On<Add, unit::Unit>,
+    mut commands: Commands,
+    unit_query: Query<&unit::Unit>,
+    teams: Res<Teams>,
+    textures: Option<Res<Textures>>,
+) {
+    let (Ok(unit), Some(textures)) = (unit_query.get(trigger.event_target()), textures) else {
+        return;
+    };
+    let texture = match teams.get(unit.team).and_then(|team| team.sprite.as_deref()) {
+        Some(name) => textures.get(name).unwrap_or_else(|| {
+            warn!("Team {} draws a missing texture {}", unit.team, name);
+            &textures.unit
+        }),
+        None => &textures.unit,
+    };
+    let mut sprite = texture.sprite();
+    sprite.color = teams.color(unit.team);
+    let mut entity = commands.entity(trigger.event_target());
+    entity.insert((sprite, texture.anchor()));
+    if let Some(animation) = texture.animation() {
+        entity.insert(animation);
+    }
+}
+
+fn toggle_palette(mut teams: ResMut<Teams>) {
+    teams.palette = match teams.palette {
+        Palette::Standard => Palette::ColorblindSafe,
+        Palette::ColorblindSafe => Palette::Standard,
+    };
+}
+
+// Keeps units in their team's color when the teams or palette change.
+fn recolor_units(teams: Res<Teams>, mut query: Query<(&unit::Unit, &mut Sprite)>) {
+    for (unit, mut sprite) in query.iter_mut() {
+        sprite.color = teams.color(unit.team);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_free_for_all() {
+        let teams = Teams::new();
+        assert!(teams.is_hostile(1, 2));
+        assert!(teams.is_hostile(3, 2));
+        assert!(teams.is_allied(2, 2));
+    }
+
+    #[test]
+    fn test_two_vs_two() {
+        let teams =
+            Teams::new()
+                .with_stance(1, 2, Stance::Allied)
+                .with_stance(4, 3, Stance::Allied);
+        assert!(teams.is_allied(2, 1));
+        assert!(teams.is_allied(3, 4));
+        assert!(teams.is_hostile(1, 3));
+        assert!(teams.is_hostile(4, 2));
+    }
+
+    #[test]
+    fn test_neutral_monsters() {
+        let mut teams = Teams::new().with_stance(1, 3, Stance::Neutral);
+        assert_eq!(teams.stance(3, 1), Stance::Neutral);
+        assert!(!teams.is_hostile(1, 3));
+        assert!(teams.is_hostile(2, 3));
+
+        // Provoked monsters turn hostile, replacing the earlier stance.
+        teams.set_stance(3, 1, Stance::Hostile);
+        assert!(teams.is_hostile(1, 3));
+    }
+
+    #[test]
+    fn test_with_team_replaces() {
+        let teams = Teams::default().with_team(2, "Green", 4, Controller::Computer);
+        assert_eq!(teams.teams.len(), 3);
+        assert_eq!(teams.get(2).map(|team| team.name.as_str()), Some("Green"));
+        assert_eq!(
+            teams.get(1).map(|team| team.controller),
+            Some(Controller::Player)
+        );
+        assert!(teams.get(4).is_none());
+    }
+
+    #[test]
+    fn test_default_monsters_are_neutral() {
+        let teams = Teams::default();
+        assert!(teams.is_hostile(1, 2));
+        assert_eq!(teams.stance(1, 3), Stance::Neutral);
+        assert_eq!(teams.stance(3, 2), Stance::Neutral);
+    }
+
+    #[test]
+    fn test_colors() {
+        let mut teams = Teams::default()
+            .with_team(3, "Green", 8, Controller::Computer)
+            .with_sprite(3, "monster");
+        assert_eq!(teams.color(1), STANDARD_COLORS[0]);
+        assert_eq!(teams.color(3), STANDARD_COLORS[2]);
+        assert_eq!(teams.color(4), Color::WHITE);
+        assert_eq!(
+            teams.get(3).and_then(|team| team.sprite.as_deref()),
+            Some("monster")
+        );
+
+        teams.palette = Palette::ColorblindSafe;
+        assert_eq!(teams.color(2), COLORBLIND_COLORS[1]);
+    }
+}
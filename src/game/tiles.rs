@@ -4,6 +4,11 @@ use super::grid;
 use crate::theme::Textures;
 use crate::util::cords;
 
+// How much darker tiles at ground level are drawn.
+const GROUND_SHADE: f32 = 0.3;
+// Brightness change for each level of elevation.
+const HEIGHT_SHADE: f32 = 0.1;
+
 pub fn plugin(app: &mut bevy::prelude::App) {
     app.add_observer(populate_grid);
 }
@@ -18,21 +23,21 @@ pub fn populate_grid(
     if let Ok((mut grid, scale, entity)) = query.get_mut(entity) {
         for index in 0..grid.spaces() {
             let location = cords::index_to_location(&grid.size(), index as usize);
+            let height = grid.height(&location);
+            let translation = scale.translation(&grid, &location, -1);
             grid.spawn(
                 &mut commands,
                 &grid::EntityKind::Tile,
                 &location,
                 entity,
                 (
-                    Tile {},
+                    Tile { height },
                     Sprite {
+                        color: Color::WHITE
+                            .darker((GROUND_SHADE - height as f32 * HEIGHT_SHADE).clamp(0.0, 1.0)),
                         ..textures.tile.sprite()
                     },
-                    Transform::from_translation(cords::location_to_translation(
-                        &location,
-                        scale.scale(),
-                        -1,
-                    )),
+                    Transform::from_translation(translation),
                     Name::new("Tile"),
                 ),
             );
@@ -41,4 +46,6 @@ pub fn populate_grid(
 }
 
 #[derive(Component, Clone, Debug, Reflect)]
-pub struct Tile {}
+pub struct Tile {
+    pub height: i32,
+}
@@ -1,20 +1,42 @@
 use bevy::prelude::*;
 
+use super::forced;
+use crate::theme::Animation;
+use crate::theme::SpriteAnimation;
+use crate::util::cords;
+
+// Units are drawn above tiles and objects.
+pub const UNIT_Z_LAYER: i32 = 1;
+// Damage multiplier for attacks into the side of a unit.
+const FLANK_BONUS: f32 = 1.25;
+// Damage multiplier for attacks into the back of a unit.
+const REAR_BONUS: f32 = 1.5;
+// Size of the marker on the side a unit faces, relative to the unit's sprite.
+const FACING_MARKER_SIZE: Vec2 = Vec2::new(0.6, 0.08);
+const FACING_MARKER_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.8);
+
 pub fn plugin(app: &mut bevy::prelude::App) {
     app.add_systems(PostUpdate, despawn_on_zero_health);
+    app.add_systems(Update, (show_facing, remove_corpses));
 
     app.register_type::<Unit>();
     app.register_type::<Movement>();
     app.register_type::<Health>();
+    app.register_type::<Attacks>();
+    app.register_type::<Facing>();
+    app.register_type::<FacingMarker>();
+    app.register_type::<Corpse>();
 }
 
 #[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
 #[require(Transform, Name::new("Unit"))]
 pub struct Unit {
     pub team: u32,
 }
 
 #[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
 pub struct Movement {
     pub spaces: u32,
 }
@@ -26,40 +48,109 @@ impl Movement {
 }
 
 #[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
 pub struct Health {
     pub current: u32,
     pub max: u32,
+    // Absorbs damage before health is lost.
+    pub shield: u32,
 }
 
 impl Health {
     pub fn new(max: u32) -> Self {
-        Health { current: max, max }
+        Health {
+            current: max,
+            max,
+            shield: 0,
+        }
+    }
+
+    pub fn with_shield(mut self, shield: u32) -> Self {
+        self.shield = shield;
+        self
     }
 
     pub fn damage(&mut self, amount: u32) {
-        self.current = self.current.saturating_sub(amount);
+        let absorbed = amount.min(self.shield);
+        self.shield -= absorbed;
+        self.current = self.current.saturating_sub(amount - absorbed);
     }
 
-    pub fn percent(&self) -> f32 {
-        self.current as f32 / self.max as f32
+    // Restores health, up to the maximum.
+    pub fn heal(&mut self, amount: u32) {
+        self.current = (self.current + amount).min(self.max);
     }
+
+    // Health and shield together, the damage needed to bring the unit down.
+    pub fn total(&self) -> u32 {
+        self.current + self.shield
+    }
+}
+
+// Triggered on an entity as it is removed for running out of health.
+#[derive(EntityEvent, Clone, Debug, Reflect)]
+pub struct Death {
+    entity: Entity,
 }
 
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+// Left behind by a unit that ran out of health, playing its death until it is removed.
+struct Corpse;
+
+// Units leave the battle as soon as they run out of health. Units with a death animation leave a
+// corpse in their place to play it.
 fn despawn_on_zero_health(
     mut commands: Commands,
-    mut query: Query<(Entity, &Health), Changed<Health>>,
+    query: Query<
+        (
+            Entity,
+            &Health,
+            Option<(&Sprite, &bevy::sprite::Anchor, &Transform, &SpriteAnimation)>,
+        ),
+        Changed<Health>,
+    >,
 ) {
-    for (entity, health) in query.iter_mut() {
-        if health.current == 0 {
+    for (entity, health, visuals) in query.iter() {
+        if health.current != 0 {
+            continue;
+        }
+        commands.trigger(Death { entity });
+        commands.entity(entity).despawn();
+        if let Some((sprite, anchor, transform, animation)) = visuals
+            && animation.has(Animation::Die)
+        {
+            let mut animation = animation.clone();
+            animation.play(Animation::Die);
+            commands.spawn((
+                Corpse,
+                sprite.clone(),
+                *anchor,
+                *transform,
+                animation,
+                Name::new("Corpse"),
+            ));
+        }
+    }
+}
+
+fn remove_corpses(mut commands: Commands, query: Query<(Entity, &SpriteAnimation), With<Corpse>>) {
+    for (entity, animation) in query.iter() {
+        if animation.finished() {
             commands.entity(entity).despawn();
         }
     }
 }
 
 #[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
 pub struct Attacks {
     pub damage: u32,
     pub range: f32,
+    // Movement forced on the target when an attack hits.
+    pub forced: Option<forced::Forced>,
+    // Name of the effect or projectile shown from the attacker to the target, from the effects file.
+    pub effect: String,
 }
 
 impl Attacks {
@@ -67,6 +158,184 @@ impl Attacks {
         Attacks {
             damage,
             range: range as f32,
+            forced: None,
+            effect: if range <= 1 { "swing" } else { "shoot" }.to_string(),
+        }
+    }
+
+    pub fn with_effect(mut self, effect: &str) -> Self {
+        self.effect = effect.to_string();
+        self
+    }
+
+    pub fn with_forced(mut self, forced: forced::Forced) -> Self {
+        self.forced = Some(forced);
+        self
+    }
+}
+
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+// The direction a unit is looking in, one of the eight neighbouring directions.
+pub struct Facing {
+    direction: IVec2,
+}
+
+// Which side of a unit an attack comes from, relative to its facing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum Side {
+    Front,
+    Flank,
+    Rear,
+}
+
+impl Side {
+    pub fn multiplier(&self) -> f32 {
+        match self {
+            Side::Front => 1.0,
+            Side::Flank => FLANK_BONUS,
+            Side::Rear => REAR_BONUS,
         }
     }
+
+    pub fn apply(&self, damage: u32) -> u32 {
+        (damage as f32 * self.multiplier()).round() as u32
+    }
+}
+
+impl Facing {
+    pub fn new(direction: IVec2) -> Self {
+        Facing {
+            direction: direction.signum(),
+        }
+    }
+
+    // Faces from one location towards another, keeping the current facing if they are the same.
+    pub fn towards(&self, from: &IVec2, to: &IVec2) -> Self {
+        if from == to {
+            self.clone()
+        } else {
+            Facing::new(to - from)
+        }
+    }
+
+    pub fn direction(&self) -> &IVec2 {
+        &self.direction
+    }
+
+    // Returns the side of a unit at the location that an attack from the attacker's location hits.
+    pub fn side(&self, location: &IVec2, attacker: &IVec2) -> Side {
+        let towards = (attacker - location).as_vec2().normalize_or_zero();
+        let alignment = self.direction.as_vec2().normalize_or_zero().dot(towards);
+        if alignment > 0.5 {
+            Side::Front
+        } else if alignment < -0.5 {
+            Side::Rear
+        } else {
+            Side::Flank
+        }
+    }
+
+    // The marker lies across the facing direction, so the rotation is offset by a quarter turn.
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_z(
+            cords::rotation_to(Vec3::ZERO, self.direction.as_vec2().extend(0.0))
+                - std::f32::consts::FRAC_PI_2,
+        )
+    }
+
+    // Places the marker at the edge of a unit's sprite on the side it faces.
+    fn marker_transform(&self, size: Vec2) -> Transform {
+        let offset = self.direction.as_vec2().normalize_or_zero() * size * 0.45;
+        Transform::from_translation(offset.extend(0.5)).with_rotation(self.rotation())
+    }
+}
+
+#[derive(Component, Clone, Debug, Reflect)]
+// Shows which way a unit faces, leaving the unit's own sprite upright.
+pub struct FacingMarker;
+
+fn show_facing(
+    mut commands: Commands,
+    query: Query<(Entity, &Facing, &Sprite, Option<&Children>), Changed<Facing>>,
+    mut marker_query: Query<&mut Transform, With<FacingMarker>>,
+) {
+    for (entity, facing, sprite, children) in query.iter() {
+        let size = sprite.custom_size.unwrap_or(Vec2::ONE);
+        let transform = facing.marker_transform(size);
+        let marker = children
+            .and_then(|children| children.iter().find(|child| marker_query.contains(*child)));
+        if let Some(mut marker) = marker.and_then(|marker| marker_query.get_mut(marker).ok()) {
+            *marker = transform;
+        } else {
+            commands.spawn((
+                Name::new("FacingMarker"),
+                FacingMarker,
+                ChildOf(entity),
+                Sprite::from_color(FACING_MARKER_COLOR, size * FACING_MARKER_SIZE),
+                transform,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_side() {
+        let facing = Facing::new(IVec2::new(0, 1));
+        let location = IVec2::new(2, 2);
+        assert_eq!(facing.side(&location, &IVec2::new(2, 3)), Side::Front);
+        assert_eq!(facing.side(&location, &IVec2::new(3, 3)), Side::Front);
+        assert_eq!(facing.side(&location, &IVec2::new(3, 2)), Side::Flank);
+        assert_eq!(facing.side(&location, &IVec2::new(1, 2)), Side::Flank);
+        assert_eq!(facing.side(&location, &IVec2::new(1, 1)), Side::Rear);
+        assert_eq!(facing.side(&location, &IVec2::new(2, 0)), Side::Rear);
+    }
+
+    #[test]
+    fn test_towards() {
+        let facing = Facing::new(IVec2::new(0, 1));
+        assert_eq!(
+            facing
+                .towards(&IVec2::new(0, 0), &IVec2::new(5, -3))
+                .direction(),
+            &IVec2::new(1, -1)
+        );
+        assert_eq!(
+            facing
+                .towards(&IVec2::new(1, 1), &IVec2::new(1, 1))
+                .direction(),
+            &IVec2::new(0, 1)
+        );
+    }
+
+    #[test]
+    fn test_side_apply() {
+        assert_eq!(Side::Front.apply(4), 4);
+        assert_eq!(Side::Flank.apply(4), 5);
+        assert_eq!(Side::Rear.apply(4), 6);
+    }
+
+    #[test]
+    fn test_heal_caps_at_max() {
+        let mut health = Health::new(10);
+        health.damage(6);
+        health.heal(4);
+        assert_eq!(health.current, 8);
+        health.heal(4);
+        assert_eq!(health.current, 10);
+    }
+
+    #[test]
+    fn test_shield_absorbs_damage() {
+        let mut health = Health::new(10).with_shield(3);
+        health.damage(2);
+        assert_eq!((health.current, health.shield), (10, 1));
+        health.damage(4);
+        assert_eq!((health.current, health.shield), (7, 0));
+        assert_eq!(health.total(), 7);
+    }
 }
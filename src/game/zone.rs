@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use super::team;
+
+pub fn plugin(app: &mut App) {
+    app.insert_resource(ZoneOfControl::default());
+
+    app.register_type::<ZoneOfControl>();
+    app.register_type::<MoveReplay>();
+}
+
+// Spaces next to a unit, including diagonals.
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(1, 1),
+    IVec2::new(0, 1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, 0),
+    IVec2::new(-1, -1),
+    IVec2::new(0, -1),
+    IVec2::new(1, -1),
+];
+
+#[derive(Resource, Clone, Debug, Reflect)]
+// Rules for moving through the spaces next to enemy units.
+pub struct ZoneOfControl {
+    // Extra movement spent leaving a space next to an enemy.
+    pub leave_cost: u32,
+    // Enemies get a free attack against units leaving the spaces next to them.
+    pub opportunity_attacks: bool,
+}
+
+impl Default for ZoneOfControl {
+    fn default() -> Self {
+        ZoneOfControl {
+            leave_cost: 1,
+            opportunity_attacks: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+// The spaces controlled by enemy units, and which enemies control each of them.
+pub struct Zone {
+    controllers: HashMap<IVec2, Vec<Entity>>,
+}
+
+impl Zone {
+    // Builds the zone controlled by units on teams hostile to the given team.
+    pub fn hostile_to(
+        teams: &team::Teams,
+        team: u32,
+        units: impl Iterator<Item = (Entity, u32, IVec2)>,
+    ) -> Self {
+        let mut controllers: HashMap<IVec2, Vec<Entity>> = HashMap::new();
+        for (entity, unit_team, location) in units {
+            if !teams.is_hostile(team, unit_team) {
+                continue;
+            }
+            for offset in NEIGHBOURS {
+                controllers
+                    .entry(location + offset)
+                    .or_default()
+                    .push(entity);
+            }
+        }
+        Zone { controllers }
+    }
+
+    pub fn contains(&self, location: &IVec2) -> bool {
+        self.controllers.contains_key(location)
+    }
+
+    pub fn controllers(&self, location: &IVec2) -> &[Entity] {
+        self.controllers
+            .get(location)
+            .map_or(&[], |controllers| controllers.as_slice())
+    }
+
+    // Returns the extra movement spent leaving the location.
+    pub fn leave_cost(&self, rules: &ZoneOfControl, location: &IVec2) -> u32 {
+        if self.contains(location) {
+            rules.leave_cost
+        } else {
+            0
+        }
+    }
+
+    // Returns the enemies that control the space being left but not the space being entered.
+    pub fn opportunities(&self, from: &IVec2, to: &IVec2) -> Vec<Entity> {
+        let entering = self.controllers(to);
+        self.controllers(from)
+            .iter()
+            .filter(|entity| !entering.contains(entity))
+            .copied()
+            .collect()
+    }
+}
+
+#[derive(Component, Clone, Debug, Reflect)]
+// The path taken by the last move of a unit, replayed while it walks, with the spaces where it left an enemy's zone of control.
+pub struct MoveReplay {
+    pub path: Vec<IVec2>,
+    pub contested: Vec<IVec2>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_ignores_own_team() {
+        let zone = Zone::hostile_to(
+            &team::Teams::default(),
+            1,
+            [
+                (Entity::from_bits(1), 1, IVec2::new(2, 2)),
+                (Entity::from_bits(2), 2, IVec2::new(6, 6)),
+            ]
+            .into_iter(),
+        );
+
+        assert!(!zone.contains(&IVec2::new(2, 3)));
+        assert!(zone.contains(&IVec2::new(5, 5)));
+        assert!(!zone.contains(&IVec2::new(6, 6)));
+    }
+
+    #[test]
+    fn test_leave_cost() {
+        let rules = ZoneOfControl::default();
+        let zone = Zone::hostile_to(
+            &team::Teams::default(),
+            1,
+            [(Entity::from_bits(1), 2, IVec2::ZERO)].into_iter(),
+        );
+
+        assert_eq!(zone.leave_cost(&rules, &IVec2::new(1, 1)), rules.leave_cost);
+        assert_eq!(zone.leave_cost(&rules, &IVec2::new(2, 2)), 0);
+    }
+
+    #[test]
+    fn test_opportunities() {
+        let enemy = Entity::from_bits(1);
+        let zone = Zone::hostile_to(
+            &team::Teams::default(),
+            1,
+            [(enemy, 2, IVec2::ZERO)].into_iter(),
+        );
+
+        // Moving around the enemy keeps it in reach.
+        assert!(
+            zone.opportunities(&IVec2::new(1, 0), &IVec2::new(1, 1))
+                .is_empty()
+        );
+        // Moving away from the enemy gives it a free attack.
+        assert_eq!(
+            zone.opportunities(&IVec2::new(1, 0), &IVec2::new(2, 0)),
+            vec![enemy]
+        );
+    }
+
+    #[test]
+    fn test_zone_ignores_allies_and_neutrals() {
+        let teams = team::Teams::new()
+            .with_stance(1, 2, team::Stance::Allied)
+            .with_stance(1, 3, team::Stance::Neutral);
+        let zone = Zone::hostile_to(
+            &teams,
+            1,
+            [
+                (Entity::from_bits(1), 2, IVec2::new(2, 2)),
+                (Entity::from_bits(2), 3, IVec2::new(6, 6)),
+                (Entity::from_bits(3), 4, IVec2::new(10, 10)),
+            ]
+            .into_iter(),
+        );
+
+        assert!(!zone.contains(&IVec2::new(2, 3)));
+        assert!(!zone.contains(&IVec2::new(5, 5)));
+        assert!(zone.contains(&IVec2::new(9, 9)));
+    }
+}
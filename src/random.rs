@@ -7,15 +7,61 @@ use rand::distr::Distribution;
 use rand::distr::StandardUniform;
 use rand::distr::uniform::SampleRange;
 use rand::distr::uniform::SampleUniform;
+use rand::distr::weighted::WeightedIndex;
+use rand::seq::IndexedRandom;
+use rand::seq::SliceRandom;
+use rand::seq::index;
 use rand_chacha::ChaCha8Rng;
 
+// Most dice parsed from one notation, and most sides each can have, so every total fits in an i32.
+const MAX_DICE: u32 = 1000;
+const MAX_SIDES: u32 = 1_000_000;
+
 pub fn plugin(app: &mut App) {
     app.insert_resource(RandomSource::default());
 }
 
-// RandomSource is a resource that provides random number generation capabilities.
+// Independent sequences of random numbers, so drawing from one never changes the others.
+// Cosmetic randomness can then be used freely without changing the outcome of a battle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum Stream {
+    // Generating the map and placing units and objects.
+    Map,
+    // Resolving attacks and hazards.
+    Combat,
+    // Decisions made by computer controlled units.
+    Ai,
+    // Visuals that do not affect the game.
+    Cosmetic,
+}
+
+impl Stream {
+    pub const ALL: [Stream; 4] = [Stream::Map, Stream::Combat, Stream::Ai, Stream::Cosmetic];
+
+    fn index(&self) -> usize {
+        match self {
+            Stream::Map => 0,
+            Stream::Combat => 1,
+            Stream::Ai => 2,
+            Stream::Cosmetic => 3,
+        }
+    }
+}
+
+// RandomSource is a resource that provides a random stream for each use, all derived from one seed.
 #[derive(Resource)]
-pub struct RandomSource(ChaCha8Rng);
+pub struct RandomSource {
+    seed: u64,
+    streams: [RandomStream; 4],
+}
+
+// The state of a RandomSource, stored in save files.
+// Word positions fit in a u64 until more than 2^64 words have been drawn from a stream.
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct RandomState {
+    seed: u64,
+    word_pos: [u64; 4],
+}
 
 impl Default for RandomSource {
     // Creates a new RandomSource with a seed based on the current system time in nanoseconds.
@@ -31,10 +77,73 @@ impl Default for RandomSource {
 
 impl RandomSource {
     // Creates a new RandomSource with a specific seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: Stream::ALL.map(|stream| RandomStream::from_stream(seed, stream)),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: Stream) -> &mut RandomStream {
+        &mut self.streams[stream.index()]
+    }
+
+    pub fn map(&mut self) -> &mut RandomStream {
+        self.stream(Stream::Map)
+    }
+
+    pub fn combat(&mut self) -> &mut RandomStream {
+        self.stream(Stream::Combat)
+    }
+
+    pub fn ai(&mut self) -> &mut RandomStream {
+        self.stream(Stream::Ai)
+    }
+
+    pub fn cosmetic(&mut self) -> &mut RandomStream {
+        self.stream(Stream::Cosmetic)
+    }
+
+    // Captures the position in every stream so the sequences can be continued later.
+    pub fn snapshot(&self) -> RandomState {
+        RandomState {
+            seed: self.seed,
+            word_pos: self
+                .streams
+                .each_ref()
+                .map(|stream| stream.0.get_word_pos() as u64),
+        }
+    }
+
+    // Continues every stream from a snapshot.
+    pub fn restore(&mut self, state: &RandomState) {
+        *self = Self::new(state.seed);
+        for (stream, word_pos) in self.streams.iter_mut().zip(state.word_pos) {
+            stream.0.set_word_pos(word_pos as u128);
+        }
+    }
+}
+
+// A single sequence of random numbers.
+pub struct RandomStream(ChaCha8Rng);
+
+impl RandomStream {
+    // Creates a new RandomStream with a specific seed.
     pub fn new(seed: u64) -> Self {
         Self(ChaCha8Rng::seed_from_u64(seed))
     }
 
+    // ChaCha streams with the same seed produce independent sequences.
+    fn from_stream(seed: u64, stream: Stream) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(stream.index() as u64);
+        Self(rng)
+    }
+
     // Returns a random number in the passed range.
     pub fn range<T, R>(&mut self, range: R) -> T
     where
@@ -50,13 +159,101 @@ impl RandomSource {
         self.0.random_ratio(n, d)
     }
 
-    // Returns a random value from the passed vector.
-    pub fn pick<T: Clone>(&mut self, vec: &Vec<T>) -> T {
-        if vec.is_empty() {
-            panic!("Cannot pick from an empty vector");
+    // Returns a random number in the passed range, or None if the range is empty.
+    pub fn try_range<T, R>(&mut self, range: R) -> Option<T>
+    where
+        T: SampleUniform,
+        R: SampleRange<T>,
+    {
+        (!range.is_empty()).then(|| self.0.random_range(range))
+    }
+
+    // Returns a boolean with a n/d chance of being true, or None if n/d is not a valid chance.
+    pub fn try_ratio(&mut self, n: u32, d: u32) -> Option<bool> {
+        (d > 0 && n <= d).then(|| self.0.random_ratio(n, d))
+    }
+
+    // Returns a random value from the passed slice.
+    pub fn pick<T: Clone>(&mut self, items: &[T]) -> T {
+        self.choose(items)
+            .expect("Cannot pick from an empty vector")
+            .clone()
+    }
+
+    // Returns a random value from the passed slice, or None if it is empty.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        items.choose(&mut self.0)
+    }
+
+    // Returns a random value where each value is chosen in proportion to its weight.
+    // Returns None if there are no values or all the weights are zero.
+    pub fn choose_weighted<'a, T>(&mut self, items: &'a [(T, u32)]) -> Option<&'a T> {
+        let weights = WeightedIndex::new(items.iter().map(|(_, weight)| *weight)).ok()?;
+        Some(&items[weights.sample(&mut self.0)].0)
+    }
+
+    // Shuffles the passed slice in place.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        items.shuffle(&mut self.0);
+    }
+
+    // Returns an amount of distinct values from the passed slice in a random order.
+    // Returns None if there are not enough values.
+    pub fn sample<T: Clone>(&mut self, items: &[T], amount: usize) -> Option<Vec<T>> {
+        (amount <= items.len()).then(|| {
+            index::sample(&mut self.0, items.len(), amount)
+                .into_iter()
+                .map(|i| items[i].clone())
+                .collect()
+        })
+    }
+
+    // Rolls dice written in dice notation, such as "2d6+1". Returns None if the notation is invalid.
+    pub fn roll(&mut self, notation: &str) -> Option<i32> {
+        Dice::parse(notation).and_then(|dice| self.roll_dice(&dice))
+    }
+
+    // Returns None if the dice have no sides or their total could overflow.
+    pub fn roll_dice(&mut self, dice: &Dice) -> Option<i32> {
+        // Every total lies between the lowest and highest, so once both fit no roll can overflow.
+        if dice.sides == 0 || dice.min().is_none() || dice.max().is_none() {
+            return None;
+        }
+        let rolled: i32 = (0..dice.count)
+            .map(|_| self.0.random_range(1..=dice.sides) as i32)
+            .sum();
+        Some(rolled + dice.modifier)
+    }
+
+    // Returns a value from a normal distribution using the Box-Muller transform.
+    // Returns None if the standard deviation is negative or not finite.
+    pub fn normal(&mut self, mean: f32, std_dev: f32) -> Option<f32> {
+        if !(std_dev >= 0.0 && std_dev.is_finite() && mean.is_finite()) {
+            return None;
         }
-        let index = self.0.random_range(0..vec.len());
-        vec[index].clone()
+        // Avoid zero so the logarithm stays finite.
+        let u1 = 1.0 - self.0.random::<f32>();
+        let u2 = self.0.random::<f32>();
+        let z = (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos();
+        Some(mean + z * std_dev)
+    }
+
+    // Returns a value between min and max that is most likely to be near the mode.
+    // Returns None unless min <= mode <= max.
+    pub fn triangular(&mut self, min: f32, max: f32, mode: f32) -> Option<f32> {
+        if !(min <= mode && mode <= max) {
+            return None;
+        }
+        if min == max {
+            return Some(min);
+        }
+        let u = self.0.random::<f32>();
+        let split = (mode - min) / (max - min);
+        Some(if u < split {
+            min + (u * (max - min) * (mode - min)).sqrt()
+        } else {
+            max - ((1.0 - u) * (max - min) * (max - mode)).sqrt()
+        })
     }
 
     pub fn color(&mut self) -> Color {
@@ -71,13 +268,62 @@ impl RandomSource {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Reflect)]
+// Dice written in dice notation, such as "2d6+1" for two six sided dice plus one.
+pub struct Dice {
+    pub count: u32,
+    pub sides: u32,
+    pub modifier: i32,
+}
+
+impl Dice {
+    // Parses dice notation. The count defaults to one and the modifier to zero, so "d20" is valid.
+    // Returns None for more dice or sides than can be rolled.
+    pub fn parse(notation: &str) -> Option<Dice> {
+        let notation = notation.trim().to_lowercase();
+        let (count, rest) = notation.split_once('d')?;
+        let count = if count.is_empty() {
+            1
+        } else {
+            count.parse().ok().filter(|count| *count <= MAX_DICE)?
+        };
+        let (sides, modifier) = match rest.find(['+', '-']) {
+            Some(index) => (&rest[..index], rest[index..].parse().ok()?),
+            None => (rest, 0),
+        };
+        let sides = sides
+            .parse()
+            .ok()
+            .filter(|sides| (1..=MAX_SIDES).contains(sides))?;
+        Some(Dice {
+            count,
+            sides,
+            modifier,
+        })
+    }
+
+    // Returns None if the lowest total does not fit in an i32.
+    pub fn min(&self) -> Option<i32> {
+        i32::try_from(self.count).ok()?.checked_add(self.modifier)
+    }
+
+    // Returns None if the highest total does not fit in an i32.
+    pub fn max(&self) -> Option<i32> {
+        let rolled = self.count.checked_mul(self.sides)?;
+        i32::try_from(rolled).ok()?.checked_add(self.modifier)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::random::Dice;
     use crate::random::RandomSource;
+    use crate::random::RandomStream;
+    use crate::random::Stream;
 
     #[test]
     fn test_range() {
-        let mut rng = RandomSource::new(12345);
+        let mut rng = RandomStream::new(12345);
         let mut min = 10;
         let mut max = 1;
         for _ in 0..100 {
@@ -100,7 +346,7 @@ mod tests {
 
     #[test]
     fn test_ratio() {
-        let mut rng = RandomSource::new(12345);
+        let mut rng = RandomStream::new(12345);
         let mut true_count = 0;
         let mut false_count = 0;
         for _ in 0..100 {
@@ -121,7 +367,7 @@ mod tests {
 
     #[test]
     fn test_pick() {
-        let mut rng = RandomSource::new(12345);
+        let mut rng = RandomStream::new(12345);
         let sample = vec![0, 1, 2, 3, 4];
         let mut results = vec![0; sample.len()];
         for _ in 0..100 {
@@ -140,4 +386,233 @@ mod tests {
             results
         );
     }
+
+    #[test]
+    fn test_streams_are_independent() {
+        let mut rng = RandomSource::new(12345);
+        let expected: Vec<u32> = (0..10).map(|_| rng.combat().range(0..100)).collect();
+
+        let mut rng = RandomSource::new(12345);
+        for _ in 0..10 {
+            rng.cosmetic().range(0..100);
+        }
+        let results: Vec<u32> = (0..10).map(|_| rng.combat().range(0..100)).collect();
+        assert_eq!(results, expected);
+
+        let mut rng = RandomSource::new(12345);
+        let map: Vec<u32> = (0..10).map(|_| rng.map().range(0..100)).collect();
+        assert_ne!(map, expected, "Streams should produce different sequences");
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut rng = RandomSource::new(12345);
+        rng.map().range(0..100);
+        rng.cosmetic().range(0..100);
+        let state = rng.snapshot();
+        let expected: Vec<u32> = Stream::ALL
+            .iter()
+            .map(|stream| rng.stream(*stream).range(0..100))
+            .collect();
+
+        let mut restored = RandomSource::new(1);
+        restored.restore(&state);
+        let results: Vec<u32> = Stream::ALL
+            .iter()
+            .map(|stream| restored.stream(*stream).range(0..100))
+            .collect();
+        assert_eq!(restored.seed(), 12345);
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn test_try_range() {
+        let mut rng = RandomStream::new(12345);
+        assert_eq!(rng.try_range(5..5), None);
+        for _ in 0..100 {
+            let result = rng.try_range(1..10).unwrap();
+            assert!(
+                (1..10).contains(&result),
+                "Result {} is out of range",
+                result
+            );
+        }
+        assert_eq!(rng.try_ratio(1, 0), None);
+        assert_eq!(rng.try_ratio(4, 3), None);
+    }
+
+    #[test]
+    fn test_choose() {
+        let mut rng = RandomStream::new(12345);
+        let empty: Vec<u32> = Vec::new();
+        assert_eq!(rng.choose(&empty), None);
+        let sample = [0, 1, 2];
+        let mut results = [0; 3];
+        for _ in 0..100 {
+            results[*rng.choose(&sample).unwrap()] += 1;
+        }
+        assert!(
+            results.iter().all(|&count| count > 0),
+            "Not all values were chosen, results: {:?}",
+            results
+        );
+    }
+
+    #[test]
+    fn test_choose_weighted() {
+        let mut rng = RandomStream::new(12345);
+        let sample = [(0, 1), (1, 0), (2, 9)];
+        let mut results = [0; 3];
+        for _ in 0..1000 {
+            results[*rng.choose_weighted(&sample).unwrap()] += 1;
+        }
+        assert_eq!(results[1], 0, "Zero weight value was chosen");
+        assert!(
+            results[2] > results[0] * 4,
+            "Expected mostly heavy values, results: {:?}",
+            results
+        );
+        assert_eq!(rng.choose_weighted(&[(0, 0)]), None);
+        assert_eq!(rng.choose_weighted::<u32>(&[]), None);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut rng = RandomStream::new(12345);
+        let sample: Vec<u32> = (0..20).collect();
+        let mut shuffled = sample.clone();
+        rng.shuffle(&mut shuffled);
+        assert_ne!(shuffled, sample, "Shuffle left the order unchanged");
+        shuffled.sort();
+        assert_eq!(shuffled, sample);
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut rng = RandomStream::new(12345);
+        let sample = vec![0, 1, 2, 3, 4];
+        let mut results = vec![0; sample.len()];
+        for _ in 0..100 {
+            let mut picked = rng.sample(&sample, 3).unwrap();
+            for value in picked.iter() {
+                results[*value] += 1;
+            }
+            picked.sort();
+            picked.dedup();
+            assert_eq!(picked.len(), 3, "Sampled a value twice");
+        }
+        assert!(
+            results.iter().all(|&count| count > 0),
+            "Not all values were sampled, results: {:?}",
+            results
+        );
+        assert_eq!(rng.sample(&sample, 6), None);
+    }
+
+    #[test]
+    fn test_dice_parse() {
+        assert_eq!(
+            Dice::parse("2d6+1"),
+            Some(Dice {
+                count: 2,
+                sides: 6,
+                modifier: 1
+            })
+        );
+        assert_eq!(
+            Dice::parse("d20"),
+            Some(Dice {
+                count: 1,
+                sides: 20,
+                modifier: 0
+            })
+        );
+        assert_eq!(Dice::parse("3D4-2").map(|dice| dice.modifier), Some(-2));
+        assert_eq!(Dice::parse("2d0"), None);
+        assert_eq!(Dice::parse("2x6"), None);
+        assert_eq!(Dice::parse("2d6+"), None);
+        assert_eq!(Dice::parse("1001d6"), None);
+        assert_eq!(Dice::parse("2d1000001"), None);
+    }
+
+    #[test]
+    fn test_dice_overflow() {
+        let dice = Dice::parse("1000d1000000+2147483647").unwrap();
+        assert_eq!(dice.min(), None);
+        assert_eq!(dice.max(), None);
+        assert_eq!(RandomStream::new(12345).roll_dice(&dice), None);
+
+        let dice = Dice {
+            count: u32::MAX,
+            sides: u32::MAX,
+            modifier: 0,
+        };
+        assert_eq!(dice.min(), None);
+        assert_eq!(dice.max(), None);
+        assert_eq!(
+            Dice::parse("1000d1000000-5").unwrap().max(),
+            Some(999_999_995)
+        );
+        assert_eq!(Dice::parse("2d6+1").unwrap().min(), Some(3));
+    }
+
+    #[test]
+    fn test_roll() {
+        let mut rng = RandomStream::new(12345);
+        let mut min = i32::MAX;
+        let mut max = i32::MIN;
+        for _ in 0..1000 {
+            let result = rng.roll("2d6+1").unwrap();
+            min = result.min(min);
+            max = result.max(max);
+        }
+        assert!(
+            min == 3 && max == 13,
+            "Rolls should be between 3 and 13, got {} and {}",
+            min,
+            max
+        );
+        assert_eq!(rng.roll("not dice"), None);
+    }
+
+    #[test]
+    fn test_normal() {
+        let mut rng = RandomStream::new(12345);
+        let results: Vec<f32> = (0..1000).map(|_| rng.normal(10.0, 2.0).unwrap()).collect();
+        let mean = results.iter().sum::<f32>() / results.len() as f32;
+        let within = results.iter().filter(|r| (*r - 10.0).abs() <= 2.0).count();
+        assert!((mean - 10.0).abs() < 0.5, "Mean {} is not near 10", mean);
+        // About 68% of values fall within one standard deviation.
+        assert!(
+            (600..760).contains(&within),
+            "Expected about 680 values within one deviation, got {}",
+            within
+        );
+        assert_eq!(rng.normal(0.0, -1.0), None);
+    }
+
+    #[test]
+    fn test_triangular() {
+        let mut rng = RandomStream::new(12345);
+        let mut below = 0;
+        for _ in 0..1000 {
+            let result = rng.triangular(0.0, 10.0, 2.0).unwrap();
+            assert!(
+                (0.0..=10.0).contains(&result),
+                "Result {} is out of range",
+                result
+            );
+            if result < 2.0 {
+                below += 1;
+            }
+        }
+        // 20% of values fall below a mode at 20% of the range.
+        assert!(
+            (150..250).contains(&below),
+            "Expected about 200 values below the mode, got {}",
+            below
+        );
+        assert_eq!(rng.triangular(0.0, 10.0, 11.0), None);
+        assert_eq!(rng.triangular(5.0, 5.0, 5.0), Some(5.0));
+    }
 }
@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(Update, SpriteAnimation::update);
+
+    app.register_type::<SpriteAnimation>();
+    app.register_type::<Animation>();
+    app.register_type::<Clip>();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Reflect)]
+pub enum Animation {
+    Idle,
+    Walk,
+    Attack,
+    Hurt,
+    Die,
+}
+
+#[derive(Clone, Debug, Deserialize, Reflect)]
+// Frames of a sprite sheet shown in order.
+pub struct Clip {
+    pub frames: Vec<usize>,
+    // Frames shown each second.
+    pub fps: f32,
+    // Clips that do not loop return to idle once they finish.
+    #[serde(default)]
+    pub looping: bool,
+}
+
+#[derive(Component, Clone, Debug, Reflect)]
+#[require(Sprite)]
+// Plays the named animations of a sprite sheet on a sprite.
+pub struct SpriteAnimation {
+    clips: Vec<(Animation, Clip)>,
+    current: Animation,
+    frame: usize,
+    timer: Timer,
+    // Set once dying has shown its last frame.
+    finished: bool,
+}
+
+impl SpriteAnimation {
+    pub fn new(clips: Vec<(Animation, Clip)>) -> Self {
+        let mut animation = SpriteAnimation {
+            clips,
+            current: Animation::Idle,
+            frame: 0,
+            timer: Timer::default(),
+            finished: false,
+        };
+        animation.play(Animation::Idle);
+        animation
+    }
+
+    pub fn current(&self) -> Animation {
+        self.current
+    }
+
+    // Returns true if the sprite sheet has a clip for the animation.
+    pub fn has(&self, animation: Animation) -> bool {
+        self.clips.iter().any(|(a, _)| *a == animation)
+    }
+
+    // Returns true once dying has played through.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    fn clip(&self) -> Option<&Clip> {
+        self.clips
+            .iter()
+            .find(|(animation, _)| *animation == self.current)
+            .map(|(_, clip)| clip)
+    }
+
+    // Starts an animation from its first frame. Animations without a clip are ignored.
+    // A looping animation that is already playing carries on rather than restarting.
+    pub fn play(&mut self, animation: Animation) {
+        let Some((_, clip)) = self.clips.iter().find(|(a, _)| *a == animation) else {
+            return;
+        };
+        if animation == self.current && clip.looping && self.timer.duration() > Duration::ZERO {
+            return;
+        }
+        self.timer = Timer::from_seconds(1.0 / clip.fps.max(0.001), TimerMode::Repeating);
+        self.current = animation;
+        self.frame = 0;
+        self.finished = false;
+    }
+
+    // Returns the index in the sprite sheet of the frame being shown.
+    pub fn index(&self) -> Option<usize> {
+        self.clip()
+            .and_then(|clip| clip.frames.get(self.frame))
+            .copied()
+    }
+
+    // Advances the animation by the elapsed time.
+    // Dying holds its last frame, other clips that do not loop return to idle.
+    pub fn tick(&mut self, delta: Duration) {
+        self.timer.tick(delta);
+        for _ in 0..self.timer.times_finished_this_tick() {
+            let Some(clip) = self.clip() else {
+                return;
+            };
+            let (length, looping) = (clip.frames.len(), clip.looping);
+            if self.frame + 1 < length {
+                self.frame += 1;
+            } else if looping {
+                self.frame = 0;
+            } else if self.current != Animation::Die {
+                self.play(Animation::Idle);
+                return;
+            } else {
+                self.finished = true;
+            }
+        }
+    }
+
+    fn update(time: Res<Time>, mut query: Query<(&mut SpriteAnimation, &mut Sprite)>) {
+        for (mut animation, mut sprite) in query.iter_mut() {
+            animation.tick(time.delta());
+            if let (Some(atlas), Some(index)) = (sprite.texture_atlas.as_mut(), animation.index()) {
+                atlas.index = index;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(frames: Vec<usize>, looping: bool) -> Clip {
+        Clip {
+            frames,
+            fps: 10.0,
+            looping,
+        }
+    }
+
+    fn animation() -> SpriteAnimation {
+        SpriteAnimation::new(vec![
+            (Animation::Idle, clip(vec![0, 1], true)),
+            (Animation::Attack, clip(vec![4, 5, 6], false)),
+            (Animation::Die, clip(vec![8, 9], false)),
+        ])
+    }
+
+    #[test]
+    fn test_loops_idle() {
+        let mut animation = animation();
+        assert_eq!(animation.index(), Some(0));
+        animation.tick(Duration::from_millis(150));
+        assert_eq!(animation.index(), Some(1));
+        animation.tick(Duration::from_millis(100));
+        assert_eq!(animation.index(), Some(0));
+    }
+
+    #[test]
+    fn test_returns_to_idle() {
+        let mut animation = animation();
+        animation.play(Animation::Attack);
+        assert_eq!(animation.index(), Some(4));
+        animation.tick(Duration::from_millis(250));
+        assert_eq!(animation.index(), Some(6));
+        animation.tick(Duration::from_millis(100));
+        assert_eq!(animation.current(), Animation::Idle);
+        assert_eq!(animation.index(), Some(0));
+    }
+
+    #[test]
+    fn test_die_holds_last_frame() {
+        let mut animation = animation();
+        animation.play(Animation::Die);
+        animation.tick(Duration::from_millis(150));
+        assert_eq!(animation.index(), Some(9));
+        assert!(!animation.finished());
+        animation.tick(Duration::from_millis(500));
+        assert_eq!(animation.current(), Animation::Die);
+        assert_eq!(animation.index(), Some(9));
+        assert!(animation.finished());
+    }
+
+    #[test]
+    fn test_missing_clip_is_ignored() {
+        let mut animation = animation();
+        animation.play(Animation::Walk);
+        assert_eq!(animation.current(), Animation::Idle);
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use bevy::asset::AssetLoader;
+use bevy::asset::LoadContext;
+use bevy::asset::LoadState;
+use bevy::asset::io::Reader;
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+
+// Reads a data file from the assets folder into a resource, which the game waits on while loading.
+// The resource follows the file when it changes.
+pub fn plugin<T: DataFile>(app: &mut App) {
+    app.init_asset::<T>();
+    app.register_asset_loader(RonLoader::<T>::default());
+    app.init_resource::<DataProgress>();
+    app.world_mut().resource_mut::<DataProgress>().total += 1;
+
+    app.add_systems(PreStartup, start_loading::<T>);
+    app.add_systems(Update, update_resource::<T>);
+}
+
+// A file of game data written in RON.
+pub trait DataFile: Asset + Resource + Clone + DeserializeOwned {
+    // Path of the file within the assets folder.
+    const PATH: &'static str;
+    // Copy of the file built into the game, used when the file can not be loaded.
+    const BUILT_IN: &'static str;
+
+    fn built_in() -> Self {
+        ron::from_str(Self::BUILT_IN).expect("The built in data files are valid")
+    }
+}
+
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+#[reflect(Resource)]
+// How many of the data files have been read, from their file or the built in copy.
+pub struct DataProgress {
+    pub loaded: usize,
+    pub total: usize,
+}
+
+#[derive(Debug)]
+pub enum RonError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl fmt::Display for RonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RonError::Io(error) => write!(f, "Failed to read the file: {}", error),
+            RonError::Parse(error) => write!(f, "Failed to parse the file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for RonError {}
+
+#[derive(TypePath)]
+// Loads RON files as the data file type asked for.
+pub struct RonLoader<T>(PhantomData<T>);
+
+impl<T> Default for RonLoader<T> {
+    fn default() -> Self {
+        RonLoader(PhantomData)
+    }
+}
+
+impl<T: DataFile> AssetLoader for RonLoader<T> {
+    type Asset = T;
+    type Settings = ();
+    type Error = RonError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<T, RonError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.map_err(RonError::Io)?;
+        ron::de::from_bytes(&bytes).map_err(RonError::Parse)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+#[derive(Resource)]
+struct DataHandle<T: DataFile>(Handle<T>);
+
+fn start_loading<T: DataFile>(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(DataHandle(asset_server.load::<T>(T::PATH)));
+}
+
+// Copies the file into its resource once it is loaded or changes. Files that fail to load before
+// the resource exists are replaced with the built in copy.
+fn update_resource<T: DataFile>(
+    mut commands: Commands,
+    mut events: MessageReader<AssetEvent<T>>,
+    asset_server: Res<AssetServer>,
+    assets: Res<Assets<T>>,
+    handle: Option<Res<DataHandle<T>>>,
+    current: Option<Res<T>>,
+    mut progress: ResMut<DataProgress>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+    let mut data = None;
+    for event in events.read() {
+        if let AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } = event
+            && *id == handle.0.id()
+        {
+            data = assets.get(*id).cloned();
+        }
+    }
+    if current.is_none()
+        && data.is_none()
+        && let Some(LoadState::Failed(error)) = asset_server.get_load_state(&handle.0)
+    {
+        warn!(
+            "Failed to load {}, using the built in copy: {}",
+            T::PATH,
+            error
+        );
+        data = Some(T::built_in());
+    }
+    let Some(data) = data else {
+        return;
+    };
+    if current.is_none() {
+        progress.loaded += 1;
+    }
+    commands.insert_resource(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Asset, Resource, Clone, Debug, Deserialize, TypePath)]
+    struct Scale {
+        scale: f32,
+    }
+
+    impl DataFile for Scale {
+        const PATH: &'static str = "theme.ron";
+        const BUILT_IN: &'static str = "(scale: 1.0)";
+    }
+
+    #[derive(Asset, Resource, Clone, Debug, Deserialize, TypePath)]
+    struct Missing;
+
+    impl DataFile for Missing {
+        const PATH: &'static str = "missing.ron";
+        const BUILT_IN: &'static str = "()";
+    }
+
+    #[test]
+    fn test_load() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+        app.add_plugins((plugin::<Scale>, plugin::<Missing>));
+        for _ in 0..1000 {
+            app.update();
+            if app.world().resource::<DataProgress>().loaded == 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        // Each type is read with its own loader, and missing files use the built in copy.
+        assert_eq!(app.world().resource::<Scale>().scale, 32.0);
+        assert!(app.world().get_resource::<Missing>().is_some());
+        assert_eq!(app.world().resource::<DataProgress>().total, 2);
+    }
+}
@@ -1,9 +1,74 @@
+use std::collections::HashMap;
+
+use bevy::asset::LoadState;
+use bevy::asset::RenderAssetUsages;
 use bevy::prelude::*;
+use bevy::render::render_resource::Extent3d;
+use bevy::render::render_resource::TextureDimension;
+use bevy::render::render_resource::TextureFormat;
+use serde::Deserialize;
+
+mod animation;
+pub mod data;
+mod svg;
+
+pub use animation::Animation;
+pub use animation::SpriteAnimation;
+
+// Copy of the manifest built into the game, used when the file can not be loaded.
+const BUILT_IN_MANIFEST: &str = include_str!("../../assets/theme.ron");
+// Textures the game always uses, which every manifest must have.
+const BUILT_IN_TEXTURES: [&str; 4] = ["attack", "swing", "tile", "unit"];
+// Checkerboard colors drawn in place of textures that could not be loaded.
+const PLACEHOLDER_COLORS: [[u8; 4]; 2] = [[255, 0, 255, 255], [0, 0, 0, 255]];
 
 pub fn plugin(app: &mut App) {
-    app.add_systems(PreStartup, Textures::load);
+    app.add_plugins((animation::plugin, svg::plugin, data::plugin::<Manifest>));
+    app.init_state::<AssetState>();
+    app.insert_resource(LoadingProgress::default());
+
+    // Textures are loaded once the manifest listing them has been read.
+    app.add_systems(
+        Update,
+        (
+            Textures::load
+                .run_if(resource_exists::<Manifest>.and(not(resource_exists::<Textures>))),
+            Textures::check_loaded.run_if(in_state(AssetState::Loading)),
+        )
+            .chain(),
+    );
+
+    app.register_type::<LoadingProgress>();
+    app.register_type::<data::DataProgress>();
+}
+
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+// The game waits in loading until every theme texture is ready to draw and every data file is read.
+pub enum AssetState {
+    #[default]
+    Loading,
+    Ready,
 }
 
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+#[reflect(Resource)]
+// How many of the theme textures and data files have finished loading, successfully or not.
+pub struct LoadingProgress {
+    pub loaded: usize,
+    pub total: usize,
+}
+
+impl LoadingProgress {
+    pub fn percent(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.loaded as f32 / self.total as f32
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
 pub enum Anchor {
     Center,
     BottomCenter,
@@ -13,6 +78,11 @@ pub struct Texture {
     handle: Handle<Image>,
     size: Vec2,
     anchor: Anchor,
+    // Layout of the frames when the texture is a sprite sheet.
+    atlas: Option<Handle<TextureAtlasLayout>>,
+    animations: Vec<(Animation, animation::Clip)>,
+    // Set once the texture could not be loaded and is drawn with the placeholder instead.
+    placeholder: bool,
 }
 
 impl Texture {
@@ -20,10 +90,46 @@ impl Texture {
         Sprite {
             image: self.handle.clone(),
             custom_size: Some(self.size),
+            texture_atlas: self.atlas.clone().map(|layout| TextureAtlas {
+                layout,
+                index: self.first_frame(),
+            }),
             ..default()
         }
     }
 
+    // The sprite anchor matching the texture's anchor.
+    pub fn anchor(&self) -> bevy::sprite::Anchor {
+        match self.anchor {
+            Anchor::Center => bevy::sprite::Anchor::CENTER,
+            Anchor::BottomCenter => bevy::sprite::Anchor::BOTTOM_CENTER,
+        }
+    }
+
+    // Returns a player for the texture's animations, if it is a sprite sheet with any.
+    pub fn animation(&self) -> Option<SpriteAnimation> {
+        (self.atlas.is_some() && !self.animations.is_empty())
+            .then(|| SpriteAnimation::new(self.animations.clone()))
+    }
+
+    // Sprite sheets start on the first idle frame.
+    fn first_frame(&self) -> usize {
+        self.animations
+            .iter()
+            .find(|(animation, _)| *animation == Animation::Idle)
+            .and_then(|(_, clip)| clip.frames.first())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // Draws the texture with a placeholder image, which has no frames to animate.
+    fn use_placeholder(&mut self, handle: Handle<Image>) {
+        self.handle = handle;
+        self.atlas = None;
+        self.animations.clear();
+        self.placeholder = true;
+    }
+
     pub fn scale(&self) -> Vec2 {
         self.size
     }
@@ -40,38 +146,291 @@ impl Texture {
     }
 }
 
+#[derive(Asset, Resource, Clone, Debug, Deserialize, TypePath)]
+// Every texture the game uses, read from the theme manifest.
+struct Manifest {
+    // Size of a grid space in pixels.
+    scale: f32,
+    // Pixels drawn for each pixel of the scale when rasterizing SVG textures.
+    #[serde(default = "default_resolution")]
+    resolution: f32,
+    textures: HashMap<String, TextureEntry>,
+}
+
+fn default_resolution() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct TextureEntry {
+    path: String,
+    // Size as a multiple of the scale.
+    size: (f32, f32),
+    anchor: Anchor,
+    #[serde(default)]
+    sheet: Option<Sheet>,
+    #[serde(default)]
+    animations: HashMap<Animation, animation::Clip>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+// Splits a texture into a grid of frames, each the given size in pixels.
+// SVG sheets are drawn with frames of this size multiplied by the resolution.
+struct Sheet {
+    frame: (u32, u32),
+    columns: u32,
+    rows: u32,
+}
+
+impl data::DataFile for Manifest {
+    const PATH: &'static str = "theme.ron";
+    const BUILT_IN: &'static str = BUILT_IN_MANIFEST;
+}
+
+impl Manifest {
+    fn texture(
+        &self,
+        name: &str,
+        asset_server: &AssetServer,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> Texture {
+        let Some(entry) = self.textures.get(name) else {
+            warn!("Theme manifest is missing the {} texture", name);
+            return Texture {
+                handle: Handle::default(),
+                size: Vec2::splat(self.scale),
+                anchor: Anchor::Center,
+                atlas: None,
+                animations: Vec::new(),
+                placeholder: false,
+            };
+        };
+        let size = Vec2::new(entry.size.0, entry.size.1) * self.scale;
+        let is_svg = entry.path.ends_with(".svg");
+        let frame = |sheet: &Sheet| {
+            let frame = UVec2::new(sheet.frame.0, sheet.frame.1);
+            if is_svg {
+                (frame.as_vec2() * self.resolution).round().as_uvec2()
+            } else {
+                frame
+            }
+        };
+        let handle = if is_svg {
+            let pixels = match &entry.sheet {
+                Some(sheet) => frame(sheet) * UVec2::new(sheet.columns, sheet.rows),
+                None => (size * self.resolution).round().as_uvec2(),
+            };
+            asset_server.load_with_settings(
+                entry.path.clone(),
+                move |settings: &mut svg::SvgSettings| {
+                    settings.size = Some(pixels);
+                },
+            )
+        } else {
+            asset_server.load(entry.path.clone())
+        };
+        Texture {
+            handle,
+            size,
+            anchor: entry.anchor,
+            atlas: entry.sheet.as_ref().map(|sheet| {
+                layouts.add(TextureAtlasLayout::from_grid(
+                    frame(sheet),
+                    sheet.columns,
+                    sheet.rows,
+                    None,
+                    None,
+                ))
+            }),
+            animations: entry
+                .animations
+                .iter()
+                .map(|(animation, clip)| (*animation, clip.clone()))
+                .collect(),
+            placeholder: false,
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct Textures {
     pub tile: Texture,
     pub unit: Texture,
     pub attack: Texture,
     pub swing: Texture,
+    // Every other texture in the manifest, such as unit variants for a team, by name.
+    named: HashMap<String, Texture>,
 }
 
 impl Textures {
-    fn load(mut commands: Commands, asset_server: Res<AssetServer>) {
-        let scale = 32.0;
+    fn load(
+        mut commands: Commands,
+        manifest: Res<Manifest>,
+        asset_server: Res<AssetServer>,
+        mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    ) {
+        let mut texture = |name: &str| manifest.texture(name, &asset_server, &mut layouts);
+        let named = manifest
+            .textures
+            .keys()
+            .filter(|name| !BUILT_IN_TEXTURES.contains(&name.as_str()))
+            .map(|name| (name.clone(), texture(name)))
+            .collect();
         commands.insert_resource(Textures {
-            attack: Texture {
-                handle: asset_server.load("tiles/attack.png"),
-                size: Vec2::splat(scale / 2.0),
-                anchor: Anchor::Center,
-            },
-            swing: Texture {
-                handle: asset_server.load("tiles/swing.png"),
-                size: Vec2::new(scale / 4.0, scale / 2.0),
-                anchor: Anchor::Center,
-            },
-            tile: Texture {
-                handle: asset_server.load("tiles/tile.png"),
-                size: Vec2::splat(scale),
-                anchor: Anchor::Center,
-            },
-            unit: Texture {
-                handle: asset_server.load("tiles/unit.png"),
-                size: Vec2::splat(scale),
-                anchor: Anchor::Center,
-            },
+            attack: texture("attack"),
+            swing: texture("swing"),
+            tile: texture("tile"),
+            unit: texture("unit"),
+            named,
         });
     }
+
+    // Looks up a texture by its name in the theme manifest.
+    pub fn get(&self, name: &str) -> Option<&Texture> {
+        match name {
+            "attack" => Some(&self.attack),
+            "swing" => Some(&self.swing),
+            "tile" => Some(&self.tile),
+            "unit" => Some(&self.unit),
+            _ => self.named.get(name),
+        }
+    }
+
+    fn all_mut(&mut self) -> Vec<&mut Texture> {
+        [
+            &mut self.tile,
+            &mut self.unit,
+            &mut self.attack,
+            &mut self.swing,
+        ]
+        .into_iter()
+        .chain(self.named.values_mut())
+        .collect()
+    }
+
+    // Counts the textures that have finished loading, replacing any that failed with a placeholder.
+    // Moves on once every texture and data file is ready.
+    fn check_loaded(
+        asset_server: Res<AssetServer>,
+        textures: Option<ResMut<Textures>>,
+        data: Res<data::DataProgress>,
+        mut images: ResMut<Assets<Image>>,
+        mut progress: ResMut<LoadingProgress>,
+        mut next_state: ResMut<NextState<AssetState>>,
+    ) {
+        let mut loaded = data.loaded;
+        let mut total = data.total;
+        // Textures are counted once the manifest listing them has been read.
+        match textures {
+            Some(mut textures) => {
+                let textures = textures.all_mut();
+                total += textures.len();
+                for texture in textures {
+                    if texture.placeholder {
+                        loaded += 1;
+                        continue;
+                    }
+                    let path = texture
+                        .handle
+                        .path()
+                        .map_or("an unnamed texture".into(), |path| path.to_string());
+                    match asset_server.get_load_state(&texture.handle) {
+                        Some(LoadState::Loaded) => loaded += 1,
+                        Some(LoadState::NotLoaded | LoadState::Loading) => {}
+                        Some(LoadState::Failed(error)) => {
+                            warn!("Failed to load {}, using a placeholder: {}", path, error);
+                            texture.use_placeholder(images.add(placeholder_image()));
+                            loaded += 1;
+                        }
+                        // Handles the asset server does not know about were never given a file to load.
+                        None => {
+                            warn!("No file to load for {}, using a placeholder", path);
+                            texture.use_placeholder(images.add(placeholder_image()));
+                            loaded += 1;
+                        }
+                    }
+                }
+            }
+            None => total += BUILT_IN_TEXTURES.len(),
+        }
+        if (progress.loaded, progress.total) != (loaded, total) {
+            progress.loaded = loaded;
+            progress.total = total;
+            info!("Loaded {} of {} textures and data files", loaded, total);
+        }
+        if loaded == total {
+            next_state.set(AssetState::Ready);
+        }
+    }
+}
+
+// A small checkerboard that stands out wherever a texture is missing.
+fn placeholder_image() -> Image {
+    let data = (0..4)
+        .flat_map(|index| PLACEHOLDER_COLORS[(index + index / 2) % 2])
+        .collect();
+    Image::new(
+        Extent3d {
+            width: 2,
+            height: 2,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_manifest() {
+        let manifest: Manifest = ron::from_str(BUILT_IN_MANIFEST).unwrap();
+        for name in BUILT_IN_TEXTURES {
+            assert!(manifest.textures.contains_key(name), "Missing {}", name);
+        }
+        assert_eq!(manifest.textures["swing"].size, (0.25, 0.5));
+    }
+
+    #[test]
+    fn test_sheet_entry() {
+        let entry: TextureEntry = ron::from_str(
+            "(
+                path: \"tiles/unit.png\",
+                size: (1.0, 1.0),
+                anchor: BottomCenter,
+                sheet: Some((frame: (32, 32), columns: 4, rows: 2)),
+                animations: {
+                    Idle: (frames: [0, 1], fps: 4.0, looping: true),
+                    Attack: (frames: [4, 5, 6], fps: 12.0),
+                },
+            )",
+        )
+        .unwrap();
+        assert_eq!(entry.sheet.map(|sheet| sheet.columns), Some(4));
+        assert!(entry.animations[&Animation::Idle].looping);
+        assert!(!entry.animations[&Animation::Attack].looping);
+    }
+
+    #[test]
+    fn test_placeholder_image() {
+        let image = placeholder_image();
+        assert_eq!(image.size(), UVec2::new(2, 2));
+        let data = image.data.unwrap();
+        // Diagonal pixels share a color, neighbouring pixels differ.
+        assert_eq!(data[0..4], data[12..16]);
+        assert_ne!(data[0..4], data[4..8]);
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut progress = LoadingProgress::default();
+        assert_eq!(progress.percent(), 1.0);
+        progress.total = 4;
+        progress.loaded = 1;
+        assert_eq!(progress.percent(), 0.25);
+    }
 }
@@ -0,0 +1,147 @@
+use std::fmt;
+
+use bevy::asset::AssetLoader;
+use bevy::asset::LoadContext;
+use bevy::asset::RenderAssetUsages;
+use bevy::asset::io::Reader;
+use bevy::prelude::*;
+use bevy::render::render_resource::Extent3d;
+use bevy::render::render_resource::TextureDimension;
+use bevy::render::render_resource::TextureFormat;
+use resvg::tiny_skia;
+use resvg::usvg;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub fn plugin(app: &mut App) {
+    app.init_asset_loader::<SvgLoader>();
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct SvgSettings {
+    // Size of the image in pixels. The image keeps the size given in the file when not set.
+    pub size: Option<UVec2>,
+}
+
+#[derive(Debug)]
+pub enum SvgError {
+    Io(std::io::Error),
+    Parse(usvg::Error),
+    // The image has no pixels to draw, or is too large to draw.
+    Size(UVec2),
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgError::Io(error) => write!(f, "Failed to read the file: {}", error),
+            SvgError::Parse(error) => write!(f, "Failed to parse the SVG: {}", error),
+            SvgError::Size(size) => write!(f, "Can not draw an image of {}x{}", size.x, size.y),
+        }
+    }
+}
+
+impl std::error::Error for SvgError {}
+
+#[derive(Default, TypePath)]
+// Loads SVG files as images, drawn at the size asked for in the settings.
+pub struct SvgLoader;
+
+impl AssetLoader for SvgLoader {
+    type Asset = Image;
+    type Settings = SvgSettings;
+    type Error = SvgError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        settings: &SvgSettings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Image, SvgError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.map_err(SvgError::Io)?;
+        rasterize(&bytes, settings.size)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svg"]
+    }
+}
+
+// Draws an SVG into an image of the given size, stretching it to fit.
+pub fn rasterize(data: &[u8], size: Option<UVec2>) -> Result<Image, SvgError> {
+    let tree = usvg::Tree::from_data(data, &usvg::Options::default()).map_err(SvgError::Parse)?;
+    let natural = tree.size();
+    let size = size.unwrap_or_else(|| {
+        UVec2::new(
+            natural.width().ceil() as u32,
+            natural.height().ceil() as u32,
+        )
+    });
+    let mut pixmap = tiny_skia::Pixmap::new(size.x, size.y).ok_or(SvgError::Size(size))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(
+            size.x as f32 / natural.width(),
+            size.y as f32 / natural.height(),
+        ),
+        &mut pixmap.as_mut(),
+    );
+    // The drawn pixels have their alpha multiplied in, images expect it kept apart.
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    Ok(Image::new(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="4">
+        <rect x="0" y="0" width="2" height="4" fill="#ff0000"/>
+    </svg>"##;
+
+    #[test]
+    fn test_rasterize_at_size() {
+        let image = rasterize(SQUARE.as_bytes(), Some(UVec2::new(8, 8))).unwrap();
+        assert_eq!(image.size(), UVec2::new(8, 8));
+        let data = image.data.unwrap();
+        // The left half is filled red, the right half is left clear.
+        assert_eq!(data[0..4], [255, 0, 0, 255]);
+        assert_eq!(data[28..32], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_rasterize_natural_size() {
+        let image = rasterize(SQUARE.as_bytes(), None).unwrap();
+        assert_eq!(image.size(), UVec2::new(4, 4));
+    }
+
+    #[test]
+    fn test_rasterize_errors() {
+        assert!(matches!(
+            rasterize(b"not an svg", None),
+            Err(SvgError::Parse(_))
+        ));
+        assert!(matches!(
+            rasterize(SQUARE.as_bytes(), Some(UVec2::ZERO)),
+            Err(SvgError::Size(_))
+        ));
+    }
+}
@@ -48,3 +48,32 @@ pub fn location_within(start: &IVec2, end: &IVec2, location: &IVec2) -> bool {
 pub fn location_to_translation(location: &IVec2, scale: &IVec2, z: i32) -> Vec3 {
     (location * scale).extend(z).as_vec3()
 }
+
+#[inline]
+// Converts a 2D world position to the nearest grid location.
+pub fn translation_to_location(translation: &Vec2, scale: &IVec2) -> IVec2 {
+    (translation / scale.as_vec2()).round().as_ivec2()
+}
+
+#[inline]
+// Returns the grid locations on a line between two locations, including both ends.
+pub fn line(from: &IVec2, to: &IVec2) -> Vec<IVec2> {
+    let delta = (to - from).abs();
+    let step = (to - from).signum();
+    let mut error = delta.x - delta.y;
+    let mut location = *from;
+    let mut locations = vec![location];
+    while location != *to {
+        let doubled = error * 2;
+        if doubled > -delta.y {
+            error -= delta.y;
+            location.x += step.x;
+        }
+        if doubled < delta.x {
+            error += delta.x;
+            location.y += step.y;
+        }
+        locations.push(location);
+    }
+    locations
+}